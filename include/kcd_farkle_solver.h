@@ -0,0 +1,89 @@
+#ifndef KCD_FARKLE_SOLVER_H
+#define KCD_FARKLE_SOLVER_H
+
+/* Generated by cbindgen from src/ffi.rs. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define KCD_OK 0
+
+/**
+ * A required pointer was null
+ */
+#define KCD_NULL_POINTER -1
+
+/**
+ * The score lies outside of the strategy's score domain
+ */
+#define KCD_OUT_OF_RANGE -2
+
+/**
+ * A sample held a byte other than 0 to 6
+ */
+#define KCD_INVALID_SAMPLE -3
+
+/**
+ * Something unexpected went wrong inside the solver
+ */
+#define KCD_INTERNAL_ERROR -4
+
+/**
+ * A loaded strategy. Opaque to C.
+ */
+typedef struct KcdStrategy KcdStrategy;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Loads a strategy from either a checkpoint JSON file or a directory of tables written by
+ * OptimalStrat::save_tables(). Returns null on failure.
+ *
+ * # Safety
+ * path must be a valid null terminated string.
+ */
+struct KcdStrategy *kcd_strategy_load(const char *path);
+
+/**
+ * Frees a strategy returned by kcd_strategy_load(). Null is ignored.
+ *
+ * # Safety
+ * strategy must have come from kcd_strategy_load() and not already been freed.
+ */
+void kcd_strategy_free(struct KcdStrategy *strategy);
+
+/**
+ * Writes the expected score gain with the given current score and mask of dice left into out.
+ *
+ * # Safety
+ * strategy must be a live strategy and out must be valid to write a float to.
+ */
+int kcd_strategy_query_score(const struct KcdStrategy *strategy,
+                             uint32_t score,
+                             uint8_t mask,
+                             float *out);
+
+/**
+ * Writes the dice to keep (as a sample) into selection and whether to roll again into roll_again, given
+ * the current score and the sample rolled.
+ *
+ * # Safety
+ * strategy must be a live strategy, sample must point to 6 readable bytes, selection to 6 writable bytes
+ * and roll_again must be valid to write a bool to. sample and selection may point to the same array.
+ */
+int kcd_strategy_query_decision(const struct KcdStrategy *strategy,
+                                uint32_t score,
+                                const uint8_t (*sample)[6],
+                                uint8_t (*selection)[6],
+                                bool *roll_again);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* KCD_FARKLE_SOLVER_H */
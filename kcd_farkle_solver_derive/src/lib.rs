@@ -0,0 +1,151 @@
+//! Derive macro for the `kcd_farkle_solver::hash::PerfectHashing` trait
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Fields, Type};
+
+/// Derives `PerfectHashing` for fieldless enums and for structs where every field is perfectly hashable.
+///
+/// Hash layout:
+///  - Enums hash to the index of the variant (in declaration order)
+///  - Structs combine their fields in declaration order with the first field being the most significant.
+///    I.e the same layout as the tuple implementation.
+#[proc_macro_derive(PerfectHashing)]
+pub fn derive_perfect_hashing(input: TokenStream) -> TokenStream {
+    let mut input = parse_macro_input!(input as DeriveInput);
+    let krate = quote!(::kcd_farkle_solver::hash);
+
+    // Every type parameter needs to be perfectly hashable
+    for param in input.generics.type_params_mut() {
+        param.bounds.push(parse_quote!(#krate::PerfectHashing));
+    }
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let (set_size, to_hash, from_hash, try_to_hash) = match &input.data {
+        Data::Enum(data) => {
+            if let Some(variant) = data.variants.iter().find(|v| !matches!(v.fields, Fields::Unit)) {
+                return syn::Error::new_spanned(variant, "PerfectHashing can only be derived for fieldless enums")
+                    .to_compile_error()
+                    .into();
+            }
+            let n = data.variants.len();
+            let idents: Vec<_> = data.variants.iter().map(|v| &v.ident).collect();
+            let indices: Vec<_> = (0..n).collect();
+            (
+                quote!(#n),
+                quote! {
+                    match self {
+                        #(Self::#idents => #indices,)*
+                    }
+                },
+                quote! {
+                    match n {
+                        #(#indices => Self::#idents,)*
+                        _ => panic!("Hash out of range"),
+                    }
+                },
+                // Every variant is in range
+                quote!(::core::option::Option::Some(self.to_perfhash())),
+            )
+        }
+        Data::Struct(data) => {
+            let types: Vec<&Type> = data.fields.iter().map(|f| &f.ty).collect();
+            let members: Vec<TokenStream2> = data.fields.iter().enumerate().map(|(i, f)| {
+                match &f.ident {
+                    Some(ident) => quote!(#ident),
+                    None => {
+                        let index = syn::Index::from(i);
+                        quote!(#index)
+                    }
+                }
+            }).collect();
+            let vars: Vec<_> = (0..types.len()).map(|i| format_ident!("field_{}", i)).collect();
+            let sizes: Vec<_> = types.iter().map(|ty| set_size(ty, &krate)).collect();
+            let hashes: Vec<_> = members.iter()
+                .map(|member| to_hash(quote!(&self.#member), &krate))
+                .collect();
+            let try_hashes: Vec<_> = members.iter()
+                .map(|member| quote!(usize::from(#krate::PerfectHashing::try_to_perfhash(&self.#member)?)))
+                .collect();
+            let values: Vec<_> = types.iter()
+                .map(|ty| from_hash(quote!(n % size), ty, &krate))
+                .collect();
+            let construct = match &data.fields {
+                Fields::Named(_) => quote!(Self { #(#members: #vars),* }),
+                Fields::Unnamed(_) => quote!(Self ( #(#vars),* )),
+                Fields::Unit => quote!(Self),
+            };
+            // Fields are decoded starting from the least significant (the last field)
+            let decode = vars.iter().zip(sizes.iter()).zip(values.iter()).rev().map(|((var, size), value)| {
+                quote! {
+                    let size: usize = #size;
+                    let #var = #value;
+                    n = n / size;
+                }
+            });
+            (
+                quote! {{
+                    let size: usize = 1;
+                    #(let size = #krate::checked_set_size(size, #sizes);)*
+                    size
+                }},
+                quote! {
+                    let mut hash: usize = 0;
+                    #(hash = hash * #sizes + #hashes;)*
+                    hash
+                },
+                quote! {
+                    let mut n = n;
+                    #(#decode)*
+                    let _ = n;
+                    #construct
+                },
+                quote! {
+                    let mut hash: usize = 0;
+                    #(hash = hash * #sizes + #try_hashes;)*
+                    ::core::option::Option::Some(#krate::PerfectHash::new(hash))
+                },
+            )
+        }
+        Data::Union(_) => {
+            return syn::Error::new_spanned(&input.ident, "PerfectHashing can't be derived for unions")
+                .to_compile_error()
+                .into();
+        }
+    };
+
+    return quote! {
+        impl #impl_generics #krate::PerfectHashing for #name #ty_generics #where_clause {
+            const SET_SIZE: usize = #set_size;
+
+            fn to_perfhash(&self) -> #krate::PerfectHash<Self> {
+                let hash: usize = { #to_hash };
+                return #krate::PerfectHash::new(hash);
+            }
+            fn from_perfhash(hash: #krate::PerfectHash<Self>) -> Self {
+                let n: usize = hash.into();
+                return { #from_hash };
+            }
+            fn try_to_perfhash(&self) -> ::core::option::Option<#krate::PerfectHash<Self>> {
+                return { #try_to_hash };
+            }
+        }
+    }.into();
+}
+
+/// Expression for the amount of values the given type can take
+fn set_size(ty: &Type, krate: &TokenStream2) -> TokenStream2 {
+    return quote!(<#ty as #krate::PerfectHashing>::SET_SIZE);
+}
+
+/// Expression hashing the value (given by reference)
+fn to_hash(value: TokenStream2, krate: &TokenStream2) -> TokenStream2 {
+    return quote!(usize::from(#krate::PerfectHashing::to_perfhash(#value)));
+}
+
+/// Expression recovering a value of the given type from its hash
+fn from_hash(hash: TokenStream2, ty: &Type, krate: &TokenStream2) -> TokenStream2 {
+    return quote!(<#ty as #krate::PerfectHashing>::from_perfhash(#krate::PerfectHash::new(#hash)));
+}
@@ -0,0 +1,101 @@
+//! Tests the hash layout of derived PerfectHashing implementations
+
+use kcd_farkle_solver::farkle::{DiceSide, FarkleScore};
+use kcd_farkle_solver::hash::{PerfectHash, PerfectHashing};
+use std::fmt::Debug;
+
+#[derive(PerfectHashing, Debug, Clone, Copy, PartialEq)]
+enum Colour {
+    Red,
+    Green,
+    Blue,
+}
+
+#[derive(PerfectHashing, Debug, Clone, PartialEq)]
+struct Named {
+    colour: Colour,
+    side: Option<DiceSide>,
+    flags: [bool; 2],
+}
+
+#[derive(PerfectHashing, Debug, Clone, PartialEq)]
+struct Tuple(bool, Colour, DiceSide);
+
+#[derive(PerfectHashing, Debug, Clone, PartialEq)]
+struct Generic<T, const N: usize> {
+    values: [T; N],
+    last: T,
+}
+
+#[derive(PerfectHashing, Debug, Clone, PartialEq)]
+struct Unit;
+
+#[derive(PerfectHashing, Debug, Clone, PartialEq)]
+struct Scored {
+    score: FarkleScore,
+    colour: Colour,
+}
+
+/// Checks hashing is a bijection between every value of T and 0..SET_SIZE
+fn assert_round_trip<T: PerfectHashing + PartialEq + Debug>() {
+    for h in 0..T::SET_SIZE {
+        let value = T::from_perfhash(PerfectHash::new(h));
+        assert_eq!(usize::from(value.to_perfhash()), h, "{:?} does not hash back to {}", value, h);
+        assert_eq!(usize::from(value.try_to_perfhash().unwrap()), h);
+        assert_eq!(T::from_perfhash(value.to_perfhash()), value);
+    }
+}
+
+#[test]
+fn fieldless_enum() {
+    assert_eq!(Colour::SET_SIZE, 3);
+    assert_eq!(usize::from(Colour::Blue.to_perfhash()), 2);
+    assert_round_trip::<Colour>();
+}
+
+#[test]
+fn named_struct() {
+    assert_eq!(Named::SET_SIZE, 3 * 7 * 4);
+    assert_round_trip::<Named>();
+    // Same layout as the tuple of its fields
+    let value = Named {colour: Colour::Green, side: Some(DiceSide::Four), flags: [false, true]};
+    let tuple = (Colour::Green, Some(DiceSide::Four), [false, true]);
+    assert_eq!(usize::from(value.to_perfhash()), usize::from(tuple.to_perfhash()));
+}
+
+#[test]
+fn tuple_struct() {
+    assert_eq!(Tuple::SET_SIZE, 2 * 3 * 6);
+    assert_round_trip::<Tuple>();
+    let value = Tuple(true, Colour::Red, DiceSide::Six);
+    assert_eq!(usize::from(value.to_perfhash()), usize::from((true, Colour::Red, DiceSide::Six).to_perfhash()));
+}
+
+#[test]
+fn generic_struct() {
+    assert_eq!(<Generic<Colour, 2>>::SET_SIZE, 27);
+    assert_eq!(<Generic<bool, 0>>::SET_SIZE, 2);
+    assert_round_trip::<Generic<Colour, 2>>();
+    assert_round_trip::<Generic<Option<bool>, 3>>();
+    assert_round_trip::<Generic<bool, 0>>();
+}
+
+#[test]
+fn unit_struct() {
+    assert_eq!(Unit::SET_SIZE, 1);
+    assert_round_trip::<Unit>();
+}
+
+#[test]
+fn out_of_range_fields() {
+    assert_round_trip::<Scored>();
+    assert!(Scored {score: FarkleScore::new(5950), colour: Colour::Blue}.try_to_perfhash().is_some());
+    assert!(Scored {score: FarkleScore::new(6000), colour: Colour::Red}.try_to_perfhash().is_none());
+    assert!(Scored {score: FarkleScore::new(75), colour: Colour::Red}.try_to_perfhash().is_none());
+}
+
+#[test]
+fn rejected_inputs() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
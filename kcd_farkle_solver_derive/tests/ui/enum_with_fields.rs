@@ -0,0 +1,9 @@
+use kcd_farkle_solver::hash::PerfectHashing;
+
+#[derive(PerfectHashing)]
+enum Shape {
+    Empty,
+    Filled(bool),
+}
+
+fn main() {}
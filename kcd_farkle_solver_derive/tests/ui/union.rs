@@ -0,0 +1,9 @@
+use kcd_farkle_solver::hash::PerfectHashing;
+
+#[derive(PerfectHashing)]
+union Bits {
+    flag: bool,
+    side: u8,
+}
+
+fn main() {}
@@ -0,0 +1,197 @@
+//! Strategies driven by something outside of this crate: a human at a terminal or a remote process
+
+use crate::farkle::{best_selection, DiceSetSample, DiceSide};
+use crate::strategy::{Strategy, TurnState};
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::Mutex;
+
+/// A human player typing in their decisions.
+///
+/// Each decision is entered as the sides to keep followed by r (roll again) or b (bank).
+/// E.g "1 5 5 r" or "155r".
+pub struct HumanStrat<R: BufRead, W: Write> {
+    io: Mutex<(R, W)>,
+}
+impl<R: BufRead, W: Write> HumanStrat<R, W> {
+    pub fn new(reader: R, writer: W) -> Self {
+        return Self {io: Mutex::new((reader, writer))};
+    }
+}
+impl HumanStrat<io::StdinLock<'static>, io::Stdout> {
+    /// Creates a human player using the terminal
+    pub fn stdio() -> Self {
+        return Self::new(io::stdin().lock(), io::stdout());
+    }
+}
+impl<R: BufRead, W: Write> Strategy for HumanStrat<R, W> {
+    fn decide(&self, state: &TurnState, sample: &DiceSetSample) -> (DiceSetSample, bool) {
+        let mut guard = self.io.lock().unwrap();
+        let (reader, writer) = &mut *guard;
+        let rolled = sample.present().iter().map(|s| s.face().to_string()).collect::<Vec<_>>().join(" ");
+        loop {
+            let _ = writeln!(writer, "Score: {} (banked {}, opponent {}). Rolled: {}", state.score.score(), state.banked, state.opponent_banked, rolled);
+            let _ = write!(writer, "Keep (e.g \"1 5 r\" to roll again, \"1 5 b\" to bank): ");
+            let _ = writer.flush();
+            let mut line = String::new();
+            match reader.read_line(&mut line) {
+                // Nothing more to read so just take the best hand and bank
+                Ok(0) | Err(_) => return (best_selection(sample.clone()), false),
+                Ok(_) => {},
+            }
+            if let Some(decision) = parse_decision(&line, sample) {
+                return decision;
+            }
+            let _ = writeln!(writer, "Could not understand \"{}\"", line.trim());
+        }
+    }
+}
+
+/// Parses a decision of the form "<sides to keep> <r|b>"
+fn parse_decision(line: &str, sample: &DiceSetSample) -> Option<(DiceSetSample, bool)> {
+    let line = line.trim();
+    let roll_again = match line.chars().last()? {
+        'r' | 'R' => true,
+        'b' | 'B' => false,
+        _ => return None,
+    };
+    let mut sides = Vec::new();
+    for c in line[..line.len() - 1].chars().filter(|c| !c.is_whitespace()) {
+        sides.push(DiceSide::from_face(c)?);
+    }
+    return Some((sample.select_sides(&sides)?, roll_again));
+}
+
+/// Message sent to a remote agent asking for a decision
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RemoteRequest {
+    pub state: TurnState,
+    pub sample: DiceSetSample,
+}
+
+/// Message sent back from a remote agent with its decision
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RemoteResponse {
+    pub selection: DiceSetSample,
+    pub roll_again: bool,
+}
+
+/// A strategy living in another process. Decisions are requested with newline delimited JSON
+/// (RemoteRequest out, RemoteResponse back).
+///
+/// Any communication failure forfeits the round as an empty selection is returned.
+pub struct RemoteStrat {
+    reader: Mutex<Box<dyn BufRead + Send>>,
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+impl RemoteStrat {
+    pub fn new(reader: impl BufRead + Send + 'static, writer: impl Write + Send + 'static) -> Self {
+        return Self {reader: Mutex::new(Box::new(reader)), writer: Mutex::new(Box::new(writer))};
+    }
+
+    /// Connects to a remote agent over TCP
+    pub fn connect(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        return Ok(Self::new(BufReader::new(stream.try_clone()?), stream));
+    }
+
+    fn request(&self, request: &RemoteRequest) -> io::Result<RemoteResponse> {
+        {
+            let mut writer = self.writer.lock().unwrap();
+            serde_json::to_writer(&mut *writer, request)?;
+            writer.write_all(b"\n")?;
+            writer.flush()?;
+        }
+        let mut line = String::new();
+        self.reader.lock().unwrap().read_line(&mut line)?;
+        return Ok(serde_json::from_str(&line)?);
+    }
+}
+impl Strategy for RemoteStrat {
+    fn decide(&self, state: &TurnState, sample: &DiceSetSample) -> (DiceSetSample, bool) {
+        let request = RemoteRequest {state: *state, sample: sample.clone()};
+        match self.request(&request) {
+            Ok(response) => return (response.selection, response.roll_again),
+            Err(_) => return (DiceSetSample::default(), false),
+        }
+    }
+}
+
+/// Answers RemoteRequests with the given strategy until the reader runs out. This is the other end
+/// of a RemoteStrat.
+pub fn serve_remote<S: Strategy + ?Sized>(strategy: &S, reader: impl BufRead, mut writer: impl Write) -> io::Result<()> {
+    for line in reader.lines() {
+        let request: RemoteRequest = serde_json::from_str(&line?)?;
+        let (selection, roll_again) = strategy.decide(&request.state, &request.sample);
+        serde_json::to_writer(&mut writer, &RemoteResponse {selection, roll_again})?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::farkle::FarkleScore;
+    use crate::heuristic::{KeepRule, ThresholdStrat};
+    use std::io::Cursor;
+    use std::net::TcpListener;
+
+    fn sample() -> DiceSetSample {
+        return DiceSetSample::from_faces([1, 5, 5, 2, 3, 6]).unwrap();
+    }
+
+    #[test]
+    fn decisions_parse() {
+        assert_eq!(parse_decision("155r", &sample()), Some((DiceSetSample::from_faces([1, 5, 5, 0, 0, 0]).unwrap(), true)));
+        assert_eq!(parse_decision(" 1 5 B\n", &sample()), Some((DiceSetSample::from_faces([1, 5, 0, 0, 0, 0]).unwrap(), false)));
+        assert_eq!(parse_decision("b", &sample()), Some((DiceSetSample::default(), false)));
+        // Sides that weren't rolled, or not enough of them
+        assert_eq!(parse_decision("4r", &sample()), None);
+        assert_eq!(parse_decision("111r", &sample()), None);
+        // Not sides, or no decision
+        assert_eq!(parse_decision("7r", &sample()), None);
+        assert_eq!(parse_decision("15", &sample()), None);
+        assert_eq!(parse_decision("", &sample()), None);
+    }
+
+    #[test]
+    fn human_input() {
+        let state = TurnState::new(FarkleScore::new(250), [true; 6]);
+        let human = HumanStrat::new(Cursor::new("what\n1 5 r\n"), Vec::new());
+        assert_eq!(human.decide(&state, &sample()), (DiceSetSample::from_faces([1, 5, 0, 0, 0, 0]).unwrap(), true));
+        let (_, output) = human.io.into_inner().unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("Score: 250 (banked 0, opponent 0). Rolled: 1 5 5 2 3 6\n"));
+        assert!(output.contains("Could not understand \"what\""));
+        // Running out of input banks the best hand
+        let human = HumanStrat::new(Cursor::new(""), Vec::new());
+        assert_eq!(human.decide(&state, &sample()), (best_selection(sample()), false));
+    }
+
+    #[test]
+    fn remote_agent() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let heuristic = ThresholdStrat::uniform(300, KeepRule::Max);
+            serve_remote(&heuristic, BufReader::new(stream.try_clone().unwrap()), stream).unwrap();
+        });
+        let remote = RemoteStrat::connect(addr).unwrap();
+        let local = ThresholdStrat::uniform(300, KeepRule::Max);
+        for score in [0, 200, 400] {
+            let state = TurnState::new(FarkleScore::new(score), [true; 6]);
+            assert_eq!(remote.decide(&state, &sample()), local.decide(&state, &sample()));
+        }
+        drop(remote);
+        server.join().unwrap();
+
+        // No response forfeits the round
+        let remote = RemoteStrat::new(Cursor::new(""), Vec::new());
+        let state = TurnState::new(FarkleScore::new(0), [true; 6]);
+        assert_eq!(remote.decide(&state, &sample()), (DiceSetSample::default(), false));
+    }
+}
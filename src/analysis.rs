@@ -0,0 +1,115 @@
+//! Reports on the mistakes made in a session of played rounds compared to the optimal strategy
+
+use crate::gamelog::{AnnotatedRoll, RoundLog};
+use crate::optimal::OptimalStrat;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A single decision made during a session
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SessionDecision {
+    /// Index of the round within the session
+    pub round: usize,
+    /// Index of the roll within the round
+    pub roll: usize,
+    pub annotated: AnnotatedRoll,
+}
+
+/// Every decision made during a session compared against the optimal strategy
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MistakeReport {
+    /// Decisions in the order they were made (busts are left out as there is no decision to make)
+    pub decisions: Vec<SessionDecision>,
+    /// Total expected score lost over the session
+    pub total_loss: f32,
+}
+impl MistakeReport {
+    pub fn new(rounds: &[RoundLog], optimal: &OptimalStrat) -> Self {
+        let mut decisions = Vec::new();
+        for (round, log) in rounds.iter().enumerate() {
+            for (roll, annotated) in log.annotate(optimal).into_iter().enumerate() {
+                if annotated.optimal_decision.is_none() {
+                    continue;
+                }
+                decisions.push(SessionDecision {round, roll, annotated});
+            }
+        }
+        let total_loss = decisions.iter().map(|d| d.annotated.loss).sum();
+        return Self {decisions, total_loss};
+    }
+
+    /// Returns the decisions that lost expected score, biggest loss first
+    pub fn blunders(&self) -> Vec<&SessionDecision> {
+        let mut out: Vec<_> = self.decisions.iter().filter(|d| d.annotated.loss > 0.0).collect();
+        out.sort_by(|a, b| b.annotated.loss.total_cmp(&a.annotated.loss));
+        return out;
+    }
+}
+impl fmt::Display for MistakeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let blunders = self.blunders();
+        writeln!(f, "{} decisions made, {} lost expected score (total {:.1} points)", self.decisions.len(), blunders.len(), self.total_loss)?;
+        for (rank, decision) in blunders.iter().enumerate() {
+            let annotated = &decision.annotated;
+            let record = &annotated.record;
+            let (selection, roll_again) = annotated.optimal_decision.as_ref().unwrap();
+            writeln!(
+                f,
+                "{:>3}. round {} roll {}: score {}, rolled {}. Kept {} ({}) but optimal keeps {} ({}). Lost {:.1} points",
+                rank + 1, decision.round + 1, decision.roll + 1,
+                record.state.score.score(), record.roll,
+                record.selection, roll_or_bank(record.roll_again),
+                selection, roll_or_bank(*roll_again),
+                annotated.loss,
+            )?;
+        }
+        return Ok(());
+    }
+}
+
+fn roll_or_bank(roll_again: bool) -> &'static str {
+    if roll_again {
+        return "roll again";
+    }
+    return "bank";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::farkle::{Dice, DiceSetSample, ScoreDomain};
+    use crate::notation::parse_session;
+
+    #[test]
+    fn mistakes_ranked() {
+        let optimal: OptimalStrat = OptimalStrat::with_domain([Dice::default(); 6], ScoreDomain::new(50, 1000).unwrap());
+        let rounds = parse_session("
+            115346 115 b
+            155236 1 b
+            155236 15 b
+            224466
+        ", &[Dice::default(); 6]).unwrap();
+        let report = MistakeReport::new(&rounds, &optimal);
+        // Busts have no decision
+        assert_eq!(report.decisions.len(), 3);
+        assert_eq!((report.decisions[1].round, report.decisions[1].roll), (1, 0));
+        // Optimal keeps a single 1 and rolls again each time, so banking with 1 and a 5 loses 50 less
+        // than banking with the 1 alone
+        let blunders = report.blunders();
+        assert_eq!(blunders.iter().map(|d| d.round).collect::<Vec<_>>(), vec![1, 2, 0]);
+        assert!((blunders[0].annotated.loss - blunders[1].annotated.loss - 50.0).abs() < 1e-3);
+        assert_eq!(blunders[0].annotated.optimal_decision, Some((DiceSetSample::from_faces([1, 0, 0, 0, 0, 0]).unwrap(), true)));
+        assert_eq!(report.total_loss, report.decisions.iter().map(|d| d.annotated.loss).sum::<f32>());
+        let text = report.to_string();
+        assert!(text.starts_with("3 decisions made, 3 lost expected score (total "));
+        assert!(text.contains("  1. round 2 roll 1: score 0, rolled "));
+
+        // Keeping nothing from a roll that scores is a mistake rather than a bust
+        let mut rounds = rounds;
+        rounds[0].rolls[0].selection = DiceSetSample::default();
+        let report = MistakeReport::new(&rounds, &optimal);
+        assert_eq!(report.decisions.len(), 3);
+        assert_eq!(report.blunders()[0].round, 0);
+        assert!(report.decisions[0].annotated.loss > blunders[0].annotated.loss);
+    }
+}
@@ -0,0 +1,399 @@
+//! Estimating the weights of (potentially) biased dice from rolls observed in the game
+//!
+//! Rolls are read from a CSV where each row is a single roll:
+//! ```text
+//! die,face
+//! lucky,1
+//! lucky,5
+//! plain,3
+//! ```
+//! The header row is optional. Dice are identified by whatever label is in the first column.
+
+use crate::farkle::{Dice, DiceSide};
+use crate::numeric::Numeric;
+use std::error::Error;
+use std::fmt;
+
+/// Number of times each face was observed for a single die
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FaceCounts {
+    /// Counts for sides 1 to 6 respectively
+    pub counts: [u64; 6],
+}
+impl FaceCounts {
+    pub fn add(&mut self, side: DiceSide) {
+        self.counts[side as usize] += 1;
+    }
+
+    /// Total number of rolls observed
+    pub fn total(&self) -> u64 {
+        return self.counts.iter().sum();
+    }
+}
+
+/// Failure to parse a CSV of rolls
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RollsError {
+    /// Line (starting from 1) the error occured on
+    pub line: usize,
+    pub message: String,
+}
+impl fmt::Display for RollsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "line {}: {}", self.line, self.message);
+    }
+}
+impl Error for RollsError {}
+
+/// Parses a CSV of rolls (see the module documentation), counting up the faces seen for each die.
+/// Dice are returned in the order they first appear.
+pub fn parse_rolls_csv(text: &str) -> Result<Vec<(String, FaceCounts)>, RollsError> {
+    let mut dice: Vec<(String, FaceCounts)> = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let err = |message: String| RollsError {line: i + 1, message};
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(|f| f.trim()).collect();
+        let [label, face] = fields.as_slice() else {
+            return Err(err(format!("expected 2 fields but found {}", fields.len())));
+        };
+        let side = match face.chars().collect::<Vec<_>>().as_slice() {
+            [c] => DiceSide::from_face(*c),
+            _ => None,
+        };
+        let Some(side) = side else {
+            // Allow for a header row
+            if i == 0 {
+                continue;
+            }
+            return Err(err(format!("\"{}\" is not a face from 1 to 6", face)));
+        };
+        match dice.iter_mut().find(|(name, _)| name == label) {
+            Some((_, counts)) => counts.add(side),
+            None => {
+                let mut counts = FaceCounts::default();
+                counts.add(side);
+                dice.push((label.to_string(), counts));
+            }
+        }
+    }
+    return Ok(dice);
+}
+
+/// Result of Pearson's chi-square goodness of fit test
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChiSquareTest {
+    pub statistic: f64,
+    pub degrees_of_freedom: u32,
+    /// Probability of a statistic at least this large if the rolls came from the expected dice.
+    /// Small values are evidence of bias.
+    pub p_value: f64,
+}
+
+/// Tests whether the observed counts are consistent with the given dice
+pub fn chi_square<T: Numeric>(counts: &FaceCounts, expected: &Dice<T>) -> ChiSquareTest {
+    let total = counts.total() as f64;
+    let mut statistic = 0.0;
+    let mut degrees_of_freedom = 0;
+    for (i, &observed) in counts.counts.iter().enumerate() {
+        let expected_count = total * expected[i].to_f64();
+        // Sides that can never come up don't contribute a degree of freedom
+        if expected_count <= 0.0 {
+            continue;
+        }
+        statistic += (observed as f64 - expected_count).powi(2) / expected_count;
+        degrees_of_freedom += 1;
+    }
+    let degrees_of_freedom = degrees_of_freedom.max(2) - 1;
+    return ChiSquareTest {
+        statistic,
+        degrees_of_freedom,
+        p_value: 1.0 - regularised_gamma(degrees_of_freedom as f64 / 2.0, statistic / 2.0),
+    };
+}
+
+/// Reasons the weights of a die can't be estimated
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EstimateError {
+    /// The prior concentration given is below 1 (or not a number)
+    Concentration(f64),
+    /// The credibility given isn't between 0 and 1
+    Credibility(f64),
+    /// There are no rolls and the prior is flat, so nothing to estimate from
+    NoRolls,
+}
+impl fmt::Display for EstimateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Concentration(c) => return write!(f, "prior concentration {} must be a number of at least 1", c),
+            Self::Credibility(c) => return write!(f, "credibility {} must be between 0 and 1", c),
+            Self::NoRolls => return write!(f, "no rolls to estimate from (give a prior concentration above 1)"),
+        }
+    }
+}
+impl Error for EstimateError {}
+
+/// Estimated weights of a single die
+#[derive(Clone, Debug, PartialEq)]
+pub struct BiasEstimate {
+    pub counts: FaceCounts,
+    /// Most likely probability of each side (the posterior mode)
+    pub probabilities: [f64; 6],
+    /// Equal-tailed credible interval of each side's probability
+    pub intervals: [(f64, f64); 6],
+    /// Test of the counts against a fair die
+    pub fairness: ChiSquareTest,
+}
+impl BiasEstimate {
+    /// Estimates the probabilities of each side using a symmetric Dirichlet prior with the given concentration
+    /// (at least 1). A concentration of 1 gives the plain maximum likelihood estimate, with larger values
+    /// pulling the estimate towards a fair die.
+    ///
+    /// Credibility is the probability mass held by each interval (e.g 0.95).
+    ///
+    /// Without any rolls there is nothing to estimate from unless the concentration is above 1.
+    pub fn new(counts: FaceCounts, concentration: f64, credibility: f64) -> Result<Self, EstimateError> {
+        if !concentration.is_finite() || concentration < 1.0 {
+            return Err(EstimateError::Concentration(concentration));
+        }
+        if !(credibility > 0.0 && credibility < 1.0) {
+            return Err(EstimateError::Credibility(credibility));
+        }
+        if counts.total() == 0 && concentration == 1.0 {
+            return Err(EstimateError::NoRolls);
+        }
+        let total = counts.total() as f64;
+        // The posterior is Dirichlet(counts + concentration)
+        let posterior_total = total + 6.0 * concentration;
+        let probabilities = counts.counts.map(|n| (n as f64 + concentration - 1.0) / (posterior_total - 6.0));
+        // Each side's marginal is Beta(n + concentration, rest)
+        let intervals = counts.counts.map(|n| {
+            let a = n as f64 + concentration;
+            let b = posterior_total - a;
+            return (beta_quantile(a, b, (1.0 - credibility) / 2.0), beta_quantile(a, b, (1.0 + credibility) / 2.0));
+        });
+        return Ok(Self {counts, probabilities, intervals, fairness: chi_square(&counts, &Dice::<f64>::default())});
+    }
+
+    /// The estimated probabilities as a Dice
+    pub fn dice<T: Numeric>(&self) -> Dice<T> {
+        return Dice::try_normalised(self.probabilities.map(T::from_f64)).expect("Estimates are valid probabilities");
+    }
+}
+impl fmt::Display for BiasEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} rolls", self.counts.total())?;
+        for (i, (p, (low, high))) in self.probabilities.iter().zip(self.intervals.iter()).enumerate() {
+            writeln!(f, "  {}: {:>5} seen, p = {:.4} ({:.4} to {:.4})", i + 1, self.counts.counts[i], p, low, high)?;
+        }
+        return write!(
+            f, "  vs fair: chi-square {:.3} on {} degrees of freedom, p-value {:.4}",
+            self.fairness.statistic, self.fairness.degrees_of_freedom, self.fairness.p_value,
+        );
+    }
+}
+
+/// Natural log of the gamma function (Lanczos approximation)
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9, 676.520_368_121_885_1, -1_259.139_216_722_402_8,
+        771.323_428_777_653_1, -176.615_029_162_140_6, 12.507_343_278_686_905,
+        -0.138_571_095_265_720_12, 9.984_369_578_019_572e-6, 1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        // Reflection formula
+        return (std::f64::consts::PI / (std::f64::consts::PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let mut sum = COEFFICIENTS[0];
+    for (i, &c) in COEFFICIENTS.iter().enumerate().skip(1) {
+        sum += c / (x + i as f64);
+    }
+    let t = x + 7.5;
+    return 0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln();
+}
+
+/// Regularised lower incomplete gamma function P(a, x)
+fn regularised_gamma(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    let ln_prefix = a * x.ln() - x - ln_gamma(a);
+    if x < a + 1.0 {
+        // Series expansion
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        for n in 1..500 {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term.abs() < sum.abs() * 1e-15 {
+                break;
+            }
+        }
+        return sum * ln_prefix.exp();
+    }
+    // Continued fraction for the upper function (modified Lentz's method)
+    let tiny = 1e-300;
+    let mut b = x + 1.0 - a;
+    let mut c = 1.0 / tiny;
+    let mut d = 1.0 / b;
+    let mut h = d;
+    for n in 1..500 {
+        let an = -(n as f64) * (n as f64 - a);
+        b += 2.0;
+        d = an * d + b;
+        if d.abs() < tiny {
+            d = tiny;
+        }
+        c = b + an / c;
+        if c.abs() < tiny {
+            c = tiny;
+        }
+        d = 1.0 / d;
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < 1e-15 {
+            break;
+        }
+    }
+    return 1.0 - ln_prefix.exp() * h;
+}
+
+/// Regularised incomplete beta function I_x(a, b)
+fn regularised_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let ln_prefix = ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln();
+    // The continued fraction converges quickly on this side, otherwise use the symmetry I_x(a, b) = 1 - I_1-x(b, a)
+    if x < (a + 1.0) / (a + b + 2.0) {
+        return ln_prefix.exp() * beta_fraction(a, b, x) / a;
+    }
+    return 1.0 - ln_prefix.exp() * beta_fraction(b, a, 1.0 - x) / b;
+}
+
+/// Continued fraction used by regularised_beta() (modified Lentz's method)
+fn beta_fraction(a: f64, b: f64, x: f64) -> f64 {
+    let tiny = 1e-300;
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    if d.abs() < tiny {
+        d = tiny;
+    }
+    d = 1.0 / d;
+    let mut h = d;
+    for m in 1..500 {
+        let m = m as f64;
+        // Even step
+        let an = m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m));
+        d = 1.0 + an * d;
+        if d.abs() < tiny {
+            d = tiny;
+        }
+        c = 1.0 + an / c;
+        if c.abs() < tiny {
+            c = tiny;
+        }
+        d = 1.0 / d;
+        h *= d * c;
+        // Odd step
+        let an = -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0));
+        d = 1.0 + an * d;
+        if d.abs() < tiny {
+            d = tiny;
+        }
+        c = 1.0 + an / c;
+        if c.abs() < tiny {
+            c = tiny;
+        }
+        d = 1.0 / d;
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < 1e-15 {
+            break;
+        }
+    }
+    return h;
+}
+
+/// Value below which the given fraction of a Beta(a, b) distribution lies
+fn beta_quantile(a: f64, b: f64, q: f64) -> f64 {
+    // The CDF is increasing so bisect
+    let mut low = 0.0;
+    let mut high = 1.0;
+    for _ in 0..100 {
+        let mid = (low + high) / 2.0;
+        if regularised_beta(a, b, mid) < q {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    return (low + high) / 2.0;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_rolls() {
+        let dice = parse_rolls_csv("die,face\nlucky,1\nplain,3\n\nlucky,1\nlucky,6\n").unwrap();
+        assert_eq!(dice.len(), 2);
+        assert_eq!(dice[0], ("lucky".to_string(), FaceCounts {counts: [2, 0, 0, 0, 0, 1]}));
+        assert_eq!(dice[1], ("plain".to_string(), FaceCounts {counts: [0, 0, 1, 0, 0, 0]}));
+        assert_eq!(parse_rolls_csv("lucky,1\nlucky,7").unwrap_err().line, 2);
+        assert_eq!(parse_rolls_csv("lucky,1,2").unwrap_err().line, 1);
+    }
+
+    #[test]
+    fn special_functions() {
+        // Chi-square critical value for 5 degrees of freedom at the 5% level
+        assert!((1.0 - regularised_gamma(2.5, 11.0705 / 2.0) - 0.05).abs() < 1e-4);
+        // Beta(1, 1) is uniform and Beta(2, 2) is symmetric
+        assert!((beta_quantile(1.0, 1.0, 0.3) - 0.3).abs() < 1e-9);
+        assert!((beta_quantile(2.0, 2.0, 0.5) - 0.5).abs() < 1e-9);
+        assert!((regularised_beta(2.0, 3.0, 0.4) - 0.5248).abs() < 1e-9);
+    }
+
+    #[test]
+    fn estimate() {
+        let fair = BiasEstimate::new(FaceCounts {counts: [100; 6]}, 1.0, 0.95).unwrap();
+        assert!(fair.probabilities.iter().all(|&p| (p - 1.0 / 6.0).abs() < 1e-12));
+        assert_eq!(fair.fairness.statistic, 0.0);
+        assert!((fair.fairness.p_value - 1.0).abs() < 1e-12);
+        for &(low, high) in fair.intervals.iter() {
+            assert!(low < 1.0 / 6.0 && 1.0 / 6.0 < high);
+        }
+
+        let counts = FaceCounts {counts: [200, 80, 80, 80, 80, 80]};
+        // Plain maximum likelihood estimate
+        let biased = BiasEstimate::new(counts, 1.0, 0.95).unwrap();
+        assert!((biased.probabilities[0] - 200.0 / 600.0).abs() < 1e-12);
+        assert!(biased.fairness.p_value < 1e-6);
+        // The prior pulls towards fair
+        let shrunk = BiasEstimate::new(counts, 50.0, 0.95).unwrap();
+        assert!(shrunk.probabilities[0] < biased.probabilities[0]);
+        assert!((biased.dice::<f32>()[DiceSide::One] - 1.0 / 3.0).abs() < 1e-6);
+
+        // With no rolls the prior alone gives a fair die
+        let unseen = BiasEstimate::new(FaceCounts::default(), 2.0, 0.95).unwrap();
+        assert!(unseen.probabilities.iter().all(|&p| (p - 1.0 / 6.0).abs() < 1e-12));
+        assert!((unseen.dice::<f64>()[DiceSide::Six] - 1.0 / 6.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn estimate_errors() {
+        let counts = FaceCounts {counts: [1; 6]};
+        assert_eq!(BiasEstimate::new(FaceCounts::default(), 1.0, 0.95), Err(EstimateError::NoRolls));
+        assert_eq!(BiasEstimate::new(counts, 0.5, 0.95), Err(EstimateError::Concentration(0.5)));
+        assert!(matches!(BiasEstimate::new(counts, f64::NAN, 0.95), Err(EstimateError::Concentration(_))));
+        assert_eq!(BiasEstimate::new(counts, 1.0, 1.0), Err(EstimateError::Credibility(1.0)));
+        assert_eq!(EstimateError::Concentration(0.5).to_string(), "prior concentration 0.5 must be a number of at least 1");
+    }
+}
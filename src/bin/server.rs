@@ -0,0 +1,34 @@
+//! HTTP JSON API serving queries against a pre-solved strategy. See the server module of the library
+//! for the endpoints.
+//!
+//! Usage: `server [tables] [address]`, defaulting to the tables directory and 127.0.0.1:8080. Tables are
+//! written by `kcd_farkle_solver export-tables` and memory mapped, so starting up is instant.
+
+use kcd_farkle_solver::optimal::MappedStrat;
+use kcd_farkle_solver::server::{handle, response_body};
+use tiny_http::{Header, Response, Server};
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let tables = args.first().map(|s| s.as_str()).unwrap_or("tables");
+    let address = args.get(1).map(|s| s.as_str()).unwrap_or("127.0.0.1:8080");
+
+    println!("Opening {}", tables);
+    let optimal = MappedStrat::open(tables).expect("Failed to open tables");
+
+    let server = Server::http(address).expect("Failed to start server");
+    println!("Serving Optimal_{} on http://{}", optimal.n, address);
+    let json = Header::from_bytes("Content-Type", "application/json").unwrap();
+    for mut request in server.incoming_requests() {
+        let mut body = String::new();
+        let result = match request.as_reader().read_to_string(&mut body) {
+            Ok(_) => handle(&optimal, request.method(), request.url(), &body),
+            Err(e) => Err((400, format!("Failed to read request body: {}", e))),
+        };
+        let (status, answer) = response_body(result);
+        let response = Response::from_string(answer).with_status_code(status).with_header(json.clone());
+        if let Err(e) = request.respond(response) {
+            println!("Failed to respond: {}", e);
+        }
+    }
+}
@@ -1,13 +1,17 @@
 //! Everything to do with simulating a game of Farkle (using the variant in Kingdom Come Deliverance)
 
+use std::fmt;
 use std::ops::Index;
 use std::ops::Deref;
 
+use crate::hash::PerfectHashing;
+use crate::numeric::Numeric;
+use rand::Rng;
 use serde::Deserialize;
 use serde::Serialize;
 
 /// The face/outcome of a 6-sided die
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, PerfectHashing)]
 pub enum DiceSide {
     One = 0, 
     Two = 1, 
@@ -16,6 +20,19 @@ pub enum DiceSide {
     Five = 4, 
     Six = 5,
 }
+impl DiceSide {
+    /// The number shown on this side (1 to 6)
+    pub fn face(&self) -> u8 {
+        return (*self as u8) + 1;
+    }
+    /// Parses the number shown on a side ('1' to '6')
+    pub fn from_face(face: char) -> Option<Self> {
+        match face {
+            '1'..='6' => return Some(Self::from(face as u8 - b'1')),
+            _ => return None,
+        }
+    }
+}
 impl From<u8> for DiceSide {
     fn from(val: u8) -> Self {
         match val {
@@ -24,7 +41,7 @@ impl From<u8> for DiceSide {
             2 => Self::Three,
             3 => Self::Four,
             4 => Self::Five,
-            5 | _ => Self::Six,
+            _ => Self::Six,
         }
     }
 }
@@ -33,20 +50,59 @@ impl From<u8> for DiceSide {
 /// 
 /// Indexing is implemented for DiceSide (or usize) to retrieve the probability to retrieve that side.
 ///  - When indexing with usize, example_obj[0] would refer to probability for side 1, example_obj[1] would refer to side 2 etc...
+///
+/// Probabilities are f32 by default. See the numeric module for the other number types available.
+///
+/// Deserialising validates the probabilities the same way as try_new().
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
-pub struct Dice {
+#[serde(try_from = "DiceProbabilities<T>", bound(deserialize = "T: Numeric + Deserialize<'de>"))]
+pub struct Dice<T = f32> {
     /// Probabilities for sides 1 to 6 respectively
-    probabilities: [f32; 6]
+    probabilities: [T; 6]
 }
 impl Dice {
     /// Samples the dice according to its prescribed probabilities
-    pub fn roll(&self) -> u8 {
-        todo!()
+    pub fn roll<R: Rng + ?Sized>(&self, rng: &mut R) -> DiceSide {
+        let mut u: f32 = rng.random();
+        for (i, &prob) in self.probabilities.iter().enumerate() {
+            if u < prob {
+                return DiceSide::from(i as u8);
+            }
+            u -= prob;
+        }
+        // Only reachable through floating point error. So default to the last side
+        return DiceSide::Six;
     }
+}
+impl<T: Numeric> Dice<T> {
     /// Creates a new Die with specified probabilities. Proability array refers to sides 1 to 6 respectively (in that order).
-    pub fn new(probabilities: [f32; 6]) -> Self {
+    ///
+    /// The probabilities aren't checked. Use try_new() to validate them.
+    pub fn new(probabilities: [T; 6]) -> Self {
         Self {probabilities}
     }
+    /// Creates a new Die with specified probabilities, checking they are all finite, non-negative and sum to 1
+    /// (within PROBABILITY_TOLERANCE).
+    pub fn try_new(probabilities: [T; 6]) -> Result<Self, DiceError> {
+        let sum = Self::check_sides(&probabilities)?;
+        if (sum - 1.0).abs() > PROBABILITY_TOLERANCE {
+            return Err(DiceError::BadSum {sum});
+        }
+        return Ok(Self {probabilities});
+    }
+    /// Creates a new Die with probabilities proportional to the given (finite, non-negative) values.
+    /// I.e the values are scaled so they sum to 1.
+    pub fn try_normalised(values: [T; 6]) -> Result<Self, DiceError> {
+        Self::check_sides(&values)?;
+        let mut total = T::zero();
+        for value in values.iter() {
+            total += value.clone();
+        }
+        if total <= T::zero() {
+            return Err(DiceError::ZeroTotal);
+        }
+        return Ok(Self {probabilities: values.map(|v| v / total.clone())});
+    }
     /// Creates a new Die with specified probabilities. Weights array refers to the bias for sides 1 to 6 respectively (in that order).
     ///
     /// E.g weightings of [1,2,1,1,1,1] will give the probability distribution of:
@@ -56,40 +112,111 @@ impl Dice {
     ///  - Side 4: 1/7
     ///  - Side 5: 1/7
     ///  - Side 6: 1/7
+    ///
+    /// Panics if every weight is 0. Use try_new_with_weights() to handle that case.
     pub fn new_with_weights(weightings: [u32; 6]) -> Self {
-        let denominator: f32 = weightings.iter().sum::<u32>() as f32;
-        return Self::new(
-            weightings.map(|i| i as f32 / denominator)
-        );
+        return Self::try_new_with_weights(weightings).expect("Dice weights must not all be zero");
+    }
+    /// Same as new_with_weights() but fails if every weight is 0
+    pub fn try_new_with_weights(weightings: [u32; 6]) -> Result<Self, DiceError> {
+        let denominator = weightings.iter().sum::<u32>();
+        if denominator == 0 {
+            return Err(DiceError::ZeroTotal);
+        }
+        return Ok(Self::new(
+            weightings.map(|i| T::ratio(i, denominator))
+        ));
+    }
+    /// Checks every value is finite and non-negative, returning their sum
+    fn check_sides(values: &[T; 6]) -> Result<f64, DiceError> {
+        let mut sum = 0.0;
+        for (side, value) in values.iter().enumerate() {
+            let value = value.to_f64();
+            if !value.is_finite() {
+                return Err(DiceError::NotFinite {side: DiceSide::from(side as u8)});
+            }
+            if value < 0.0 {
+                return Err(DiceError::Negative {side: DiceSide::from(side as u8)});
+            }
+            sum += value;
+        }
+        return Ok(sum);
     }
     /// Calculates the expected outcome for this die
-    pub fn expected_roll(&self) -> f32 {
-        return (0..6).map(|i| ((i+1) as f32)*self.probabilities[i]).sum();
+    pub fn expected_roll(&self) -> T {
+        let mut total = T::zero();
+        for (i, prob) in self.probabilities.iter().enumerate() {
+            total += T::from_u32(i as u32 + 1) * prob.clone();
+        }
+        return total;
+    }
+    /// Converts the probabilities to another number type (going through f64)
+    pub fn cast<U: Numeric>(&self) -> Dice<U> {
+        return Dice::new(self.probabilities.clone().map(|p| U::from_f64(p.to_f64())));
     }
 }
-impl Default for Dice {
+impl<T: Numeric> Default for Dice<T> {
     /// A fair dice
     fn default() -> Self {
-        Self {probabilities: [1.0 / 6.0, 1.0 / 6.0, 1.0 / 6.0, 1.0 / 6.0, 1.0 / 6.0, 1.0 / 6.0]}
+        Self {probabilities: [(); 6].map(|_| T::ratio(1, 6))}
     }
 }
-impl Index<usize> for Dice {
-    type Output = f32;
+impl<T> Index<usize> for Dice<T> {
+    type Output = T;
 
     fn index(&self, ind: usize) -> &Self::Output {
         return &self.probabilities[ind];
     }
 }
-impl Index<DiceSide> for Dice {
-    type Output = f32;
+impl<T> Index<DiceSide> for Dice<T> {
+    type Output = T;
 
     fn index(&self, ind: DiceSide) -> &Self::Output {
         return &self.probabilities[ind as usize];
     }
 }
 
+/// How far the probabilities of a Dice may sum away from 1. Allows for the rounding error of floats.
+pub const PROBABILITY_TOLERANCE: f64 = 1e-4;
+
+/// Serialised form of a Dice, validated when converting into one
+#[derive(Deserialize)]
+struct DiceProbabilities<T> {
+    probabilities: [T; 6],
+}
+impl<T: Numeric> TryFrom<DiceProbabilities<T>> for Dice<T> {
+    type Error = DiceError;
+
+    fn try_from(value: DiceProbabilities<T>) -> Result<Self, Self::Error> {
+        return Self::try_new(value.probabilities);
+    }
+}
+
+/// Reasons a set of probabilities can't form a Dice
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiceError {
+    /// The side's probability is NaN or infinite
+    NotFinite {side: DiceSide},
+    Negative {side: DiceSide},
+    /// The probabilities don't add up to 1
+    BadSum {sum: f64},
+    /// Every weight given was 0, so there is nothing to normalise
+    ZeroTotal,
+}
+impl fmt::Display for DiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFinite {side} => return write!(f, "probability of side {} is not finite", side.face()),
+            Self::Negative {side} => return write!(f, "probability of side {} is negative", side.face()),
+            Self::BadSum {sum} => return write!(f, "probabilities sum to {} instead of 1", sum),
+            Self::ZeroTotal => return write!(f, "weights sum to 0"),
+        }
+    }
+}
+impl std::error::Error for DiceError {}
+
 /// A potential sample result from rolling (up to) 6 die.
-#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, PerfectHashing)]
 pub struct DiceSetSample {
     // A sample of (potentially) 6 die. Option::None indicates the dice was missing.
     pub sample: [Option<DiceSide>; 6]
@@ -99,6 +226,25 @@ impl DiceSetSample {
         return Self {sample};
     }
 
+    /// Creates a sample from the face shown on each die (1 to 6), with 0 for a missing die.
+    /// Returns None if a face is out of range.
+    pub fn from_faces(faces: [u8; 6]) -> Option<Self> {
+        let mut out = Self::default();
+        for (slot, face) in out.sample.iter_mut().zip(faces) {
+            *slot = match face {
+                0 => None,
+                1..=6 => Some(DiceSide::from(face - 1)),
+                _ => return None,
+            };
+        }
+        return Some(out);
+    }
+
+    /// The face shown on each die (1 to 6), with 0 for a missing die. Inverse of from_faces().
+    pub fn faces(&self) -> [u8; 6] {
+        return self.sample.map(|o| o.map_or(0, |side| side.face()));
+    }
+
     // Return vec of dice sides that are present in the sample
     pub fn present(&self) -> Vec<DiceSide> {
         return self.sample.iter()
@@ -110,6 +256,21 @@ impl DiceSetSample {
         return self.sample.map(|o| o.is_some());
     }
 
+    /// Selects the given sides out of this sample. Each side is matched to the first die showing it
+    /// that hasn't already been matched.
+    ///
+    /// Returns None if the sides can't all be found in this sample.
+    pub fn select_sides(&self, sides: &[DiceSide]) -> Option<DiceSetSample> {
+        let mut out = DiceSetSample::default();
+        for &side in sides {
+            let ind = self.sample.iter()
+                .zip(out.sample.iter())
+                .position(|(&rolled, selected)| rolled == Some(side) && selected.is_none())?;
+            out.sample[ind] = Some(side);
+        }
+        return Some(out);
+    }
+
     // Iterates through all possible "selections" of this sample. In otherwords, all possible
     // subsets of what is present in this sample, will be mapped to its own sample.
     pub fn iter_selections(&self) -> impl ExactSizeIterator<Item = DiceSetSample> {
@@ -131,16 +292,36 @@ impl DiceSetSample {
     }
 }
 
+impl fmt::Display for DiceSetSample {
+    /// Writes out the sides present, e.g "1153"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for side in self.present() {
+            write!(f, "{}", side.face())?;
+        }
+        return Ok(());
+    }
+}
+
 #[derive(Clone, Debug)]
 /// A set/sub-set of 6 existing die
-pub struct DiceSet<'a> {
+pub struct DiceSet<'a, T = f32> {
     // The 6 die this (sub)set is based on
-    pub dices: &'a [Dice; 6],
+    pub dices: &'a [Dice<T>; 6],
     // Boolean mask for what dice are retained in this set. true = retain, false = exclude.
     pub select_mask: [bool; 6]
 }
-impl<'a> DiceSet<'a> {
-    pub fn new(dices: &'a [Dice; 6], select_mask: [bool; 6]) -> Self {
+impl DiceSet<'_> {
+    /// Rolls all the dice present in this set
+    pub fn roll<R: Rng + ?Sized>(&self, rng: &mut R) -> DiceSetSample {
+        let mut v = DiceSetSample::default();
+        for (ind, _) in self.select_mask.iter().enumerate().filter(|&(_, bit)| *bit == true) {
+            v.sample[ind] = Some(self.dices[ind].roll(rng));
+        }
+        return v;
+    }
+}
+impl<'a, T: Numeric> DiceSet<'a, T> {
+    pub fn new(dices: &'a [Dice<T>; 6], select_mask: [bool; 6]) -> Self {
         Self {dices, select_mask}
     }
 
@@ -152,18 +333,18 @@ impl<'a> DiceSet<'a> {
     }
 
     /// Creates an iterator that iterates through all the sampling possibilities of this DiceSet along with their respective probabilities
-    pub fn iter_outcomes(&self) -> impl ExactSizeIterator<Item = (DiceSetSample, f32)> {
+    pub fn iter_outcomes(&self) -> impl ExactSizeIterator<Item = (DiceSetSample, T)> {
         // Get active number of dice
         let n = self.select_mask.iter().filter(|&bit| *bit == true).count();
         // Iterate through the sampling combinations of the active dice
         return (0..(6usize.pow(n as u32)))
             .map(move |mut i| {
-                let mut prob = 1.0;
+                let mut prob = T::one();
                 let mut v = DiceSetSample::default();
                 for (ind, _) in self.select_mask.iter().enumerate().filter(|&(_, bit)| *bit == true) {
                     let side: DiceSide = ((i % 6) as u8).into();
                     v.sample[ind] = Some(side);
-                    prob *= self.dices[ind][side];
+                    prob = prob * self.dices[ind][side].clone();
                     i = i / 6
                 }
                 return (v, prob);
@@ -173,7 +354,7 @@ impl<'a> DiceSet<'a> {
     /// Creates an iterator that iterates through all the possible (non-empty) subsets of this DiceSet.
     /// 
     /// Note that these are not strict subsets. I.e a clone of this object will be yielded somewhere along the iteration.
-    pub fn iter_subsets(&self) -> impl ExactSizeIterator<Item = DiceSet<'a>> {
+    pub fn iter_subsets(&self) -> impl ExactSizeIterator<Item = DiceSet<'a, T>> {
         // Get active number of dice
         let n_active = self.select_mask.iter().filter(|&bit| *bit == true).count();
         // Iterate through all possible subsets of the selection mask
@@ -258,7 +439,7 @@ pub fn not_busted(occurances: &[u8; 6]) -> bool {
         }
         _ => {},
     }
-    match highest_multi(&occurances) {
+    match highest_multi(occurances) {
         (0, ..) => {},
         (_, _, _) => {
             return true;
@@ -275,7 +456,8 @@ pub fn not_busted(occurances: &[u8; 6]) -> bool {
 
 /// Represents a score within a game of Farkle.
 #[repr(transparent)]
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct FarkleScore {
     // The score
     pub value: u32
@@ -296,31 +478,202 @@ impl FarkleScore {
     }
 }
 
+/// Granularity of the default ScoreDomain (also used by FarkleScore's PerfectHashing implementation)
+pub const DEFAULT_GRANULARITY: u32 = 50;
+/// Cap of the default ScoreDomain (also used by FarkleScore's PerfectHashing implementation)
+pub const DEFAULT_CAP: u32 = 6000;
+
+/// The range of scores a solver keeps track of. Every multiple of the granularity below the cap.
+///
+/// Scores within the domain are perfectly hashed to their index, i.e score / granularity.
+///
+/// Deserialising validates the domain the same way as new().
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "DomainBounds")]
+pub struct ScoreDomain {
+    granularity: u32,
+    cap: u32,
+}
+impl ScoreDomain {
+    /// Creates a domain of every multiple of granularity below cap.
+    ///
+    /// Fails if the cap isn't a (non-zero) multiple of the granularity, or if some hand can score
+    /// points that aren't a multiple of the granularity.
+    pub fn new(granularity: u32, cap: u32) -> Result<Self, ScoreDomainError> {
+        if granularity == 0 {
+            return Err(ScoreDomainError::ZeroGranularity);
+        }
+        if cap == 0 || cap % granularity != 0 {
+            return Err(ScoreDomainError::CapNotMultiple {granularity, cap});
+        }
+        if let Some(&score) = hand_scores().iter().find(|&&s| s % granularity != 0) {
+            return Err(ScoreDomainError::UnrepresentableHand {granularity, score});
+        }
+        return Ok(Self {granularity, cap});
+    }
+
+    pub fn granularity(&self) -> u32 {
+        return self.granularity;
+    }
+    pub fn cap(&self) -> u32 {
+        return self.cap;
+    }
+
+    /// Number of scores in the domain
+    pub fn len(&self) -> usize {
+        return (self.cap / self.granularity) as usize;
+    }
+    /// Domains always hold at least a score of 0
+    pub fn is_empty(&self) -> bool {
+        return false;
+    }
+
+    /// Highest score in the domain
+    pub fn max_score(&self) -> FarkleScore {
+        return FarkleScore::new(self.cap - self.granularity);
+    }
+
+    /// Returns the index of the score. None if the score isn't part of the domain.
+    pub fn index(&self, score: FarkleScore) -> Option<usize> {
+        if score.value % self.granularity != 0 || score.value >= self.cap {
+            return None;
+        }
+        return Some((score.value / self.granularity) as usize);
+    }
+    /// Returns the score at the given index
+    pub fn score(&self, index: usize) -> FarkleScore {
+        return FarkleScore::new(index as u32 * self.granularity);
+    }
+
+    /// Iterates through every score in the domain in ascending order
+    pub fn scores(&self) -> impl DoubleEndedIterator<Item = FarkleScore> + ExactSizeIterator + '_ {
+        return (0..self.len()).map(|i| self.score(i));
+    }
+}
+impl Default for ScoreDomain {
+    fn default() -> Self {
+        return Self {granularity: DEFAULT_GRANULARITY, cap: DEFAULT_CAP};
+    }
+}
+
+/// Serialised form of a ScoreDomain, validated when converting into one
+#[derive(Deserialize)]
+struct DomainBounds {
+    granularity: u32,
+    cap: u32,
+}
+impl TryFrom<DomainBounds> for ScoreDomain {
+    type Error = ScoreDomainError;
+
+    fn try_from(value: DomainBounds) -> Result<Self, Self::Error> {
+        return Self::new(value.granularity, value.cap);
+    }
+}
+
+/// Reasons a ScoreDomain can't be created
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScoreDomainError {
+    ZeroGranularity,
+    CapNotMultiple {granularity: u32, cap: u32},
+    /// Some hand scores points that can't be represented
+    UnrepresentableHand {granularity: u32, score: u32},
+}
+impl fmt::Display for ScoreDomainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ZeroGranularity => return write!(f, "score granularity must be non-zero"),
+            Self::CapNotMultiple {granularity, cap} => {
+                return write!(f, "score cap {} is not a non-zero multiple of the granularity {}", cap, granularity);
+            }
+            Self::UnrepresentableHand {granularity, score} => {
+                return write!(f, "a hand scoring {} is not a multiple of the granularity {}", score, granularity);
+            }
+        }
+    }
+}
+impl std::error::Error for ScoreDomainError {}
+
+/// Every distinct (non-zero) score a valid hand of up to 6 dice can make, in ascending order
+pub fn hand_scores() -> Vec<u32> {
+    let mut scores = Vec::new();
+    // For every way of having up to 6 dice
+    for h in 0..7usize.pow(6) {
+        let mut occurances = [0u8; 6];
+        let mut n = h;
+        for slot in occurances.iter_mut() {
+            *slot = (n % 7) as u8;
+            n = n / 7;
+        }
+        if occurances.iter().sum::<u8>() > 6 {
+            continue;
+        }
+        let sc = score(occurances).score();
+        if sc != 0 {
+            scores.push(sc);
+        }
+    }
+    scores.sort();
+    scores.dedup();
+    return scores;
+}
+
 
 /// Calculates Farkle score given the results of 6 or less dice.
 /// 
 /// Occurances array is the number of times each number occured from the set of dice.
 ///  - E.g We roll 2x 3's, 2x 4's and 1x 6. Occurances would be: [0,0,2,2,1]
 ///  - If all the occurances are >6, score calculation is not guaranteed to be correct
-pub fn score(mut occurances: [u8; 6]) -> FarkleScore {
-    let mut output: u32 = 0;
+pub fn score(occurances: [u8; 6]) -> FarkleScore {
+    return FarkleScore::new(score_breakdown(occurances).total);
+}
+
+/// A single scoring combination within a hand
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Combination {
+    /// 1 to 6
+    FullStraight,
+    /// 2 to 6
+    HighStraight,
+    /// 1 to 5
+    LowStraight,
+    /// 3 or more of the same side
+    OfAKind {side: DiceSide, count: u8},
+    SingleOne,
+    SingleFive,
+}
+
+/// How a hand's score is made up
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScoreBreakdown {
+    /// Every combination used along with the points it gave, in the order score() finds them
+    pub combinations: Vec<(Combination, u32)>,
+    /// Number of each side left over after forming the combinations
+    pub unused: [u8; 6],
+    /// Score of the hand. 0 if any dice were left unused (as with score()).
+    pub total: u32,
+}
+
+/// Breaks down the score of the results of 6 or less dice into the combinations forming it. See
+/// score() for the occurances array.
+pub fn score_breakdown(mut occurances: [u8; 6]) -> ScoreBreakdown {
+    let mut combinations = Vec::new();
     loop {
         match occurances {
             // Full straight
             [1..=u8::MAX, 1..=u8::MAX, 1..=u8::MAX, 1..=u8::MAX, 1..=u8::MAX, 1..=u8::MAX] => {
-                output += 1500;
+                combinations.push((Combination::FullStraight, 1500));
                 occurances.iter_mut().for_each(|i| *i -= 1);
                 continue;
             }
             // Partial straight (starting at 2)
             [_, 1..=u8::MAX, 1..=u8::MAX, 1..=u8::MAX, 1..=u8::MAX, 1..=u8::MAX] => {
-                output += 750;
+                combinations.push((Combination::HighStraight, 750));
                 occurances.iter_mut().skip(1).for_each(|i| *i -= 1);
                 continue;
             }
             // Partial straight (starting at 1)
             [1..=u8::MAX, 1..=u8::MAX, 1..=u8::MAX, 1..=u8::MAX, 1..=u8::MAX, _] => {
-                output += 500;
+                combinations.push((Combination::LowStraight, 500));
                 occurances.iter_mut().take(5).for_each(|i| *i -= 1);
                 continue;
             }
@@ -329,29 +682,30 @@ pub fn score(mut occurances: [u8; 6]) -> FarkleScore {
         match highest_multi(&occurances) {
             (0, ..) => {},
             (side, count, sc) => {
-                output += sc;
+                combinations.push((Combination::OfAKind {side: DiceSide::from(side - 1), count}, sc));
                 occurances[(side-1) as usize] -= count;
                 continue;
             }
         }
         if occurances[0] > 0 {
             occurances[0] -= 1;
-            output += 100;
+            combinations.push((Combination::SingleOne, 100));
             continue;
         }
         if occurances[4] > 0 {
             occurances[4] -= 1;
-            output += 50;
+            combinations.push((Combination::SingleFive, 50));
             continue;
         }
         break;
     }
+    let mut total = combinations.iter().map(|(_, sc)| sc).sum();
     // If all the dice have not been "used up" for the scoring calculation then we
     // have an invalid hand. Thus score is 0.
     if occurances != [0,0,0,0,0,0] {
-        return FarkleScore::new(0);
+        total = 0;
     }
-    return FarkleScore::new(output);
+    return ScoreBreakdown {combinations, unused: occurances, total};
 }
 
 /// Calculates the best Farkle score given the results of 6 or less dice.
@@ -428,7 +782,7 @@ pub fn best_selection(sample: DiceSetSample) -> DiceSetSample {
         }
         match highest_multi(&occurances) {
             (0, ..) => {},
-            (side, count, sc) => {
+            (side, count, _) => {
                 occurances[(side-1) as usize] -= count;
                 continue;
             }
@@ -447,12 +801,87 @@ pub fn best_selection(sample: DiceSetSample) -> DiceSetSample {
     // So to form the best hand we copy the dice sample and remove the left over dice.
     let mut out = sample.clone();
     for (side, &count) in occurances.iter().enumerate().map(|(i, c)| (DiceSide::from(i as u8), c)) {
-        for op in out.sample.iter_mut().filter(|o| **o == Some(side)) {
+        for op in out.sample.iter_mut().filter(|o| **o == Some(side)).take(count as usize) {
             *op = None;
-            if count == 0 {
-                break;
-            }
         }
     }
     return out;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dice_validation() {
+        assert!(Dice::try_new([1.0 / 6.0; 6]).is_ok());
+        assert_eq!(Dice::try_new([f32::NAN, 0.2, 0.2, 0.2, 0.2, 0.2]).unwrap_err(), DiceError::NotFinite {side: DiceSide::One});
+        assert_eq!(Dice::try_new([0.5, 0.5, 0.5, -0.5, 0.0, 0.0]).unwrap_err(), DiceError::Negative {side: DiceSide::Four});
+        assert!(matches!(Dice::try_new([0.5f32; 6]), Err(DiceError::BadSum {..})));
+        assert_eq!(Dice::<f32>::try_new_with_weights([0; 6]).unwrap_err(), DiceError::ZeroTotal);
+        assert_eq!(Dice::try_normalised([0.0f32; 6]).unwrap_err(), DiceError::ZeroTotal);
+
+        let dice = Dice::try_normalised([2.0f64, 1.0, 1.0, 1.0, 1.0, 2.0]).unwrap();
+        assert_eq!(dice[DiceSide::One], 0.25);
+        assert_eq!(dice[DiceSide::Two], 0.125);
+    }
+
+    #[test]
+    fn dice_deserialise_validation() {
+        let json = serde_json::to_string(&Dice::<f32>::default()).unwrap();
+        let dice: Dice = serde_json::from_str(&json).unwrap();
+        assert_eq!(dice[DiceSide::Three], 1.0 / 6.0);
+        assert!(serde_json::from_str::<Dice>(r#"{"probabilities":[0.5,0.5,0.5,0.5,0.5,0.5]}"#).is_err());
+        assert!(serde_json::from_str::<Dice>(r#"{"probabilities":[1.5,-0.5,0,0,0,0]}"#).is_err());
+    }
+
+    #[test]
+    fn sample_faces() {
+        let sample = DiceSetSample::from_faces([1, 0, 6, 0, 5, 5]).unwrap();
+        assert_eq!(sample.present(), vec![DiceSide::One, DiceSide::Six, DiceSide::Five, DiceSide::Five]);
+        assert_eq!(sample.faces(), [1, 0, 6, 0, 5, 5]);
+        assert_eq!(DiceSetSample::from_faces([1, 2, 3, 4, 5, 7]), None);
+    }
+
+    #[test]
+    fn best_selection_keeps_scoring_dice() {
+        // Only the left over dice are removed, every scoring die is kept
+        let sample = DiceSetSample::from_faces([1, 5, 2, 2, 3, 6]).unwrap();
+        assert_eq!(best_selection(sample).faces(), [1, 5, 0, 0, 0, 0]);
+        let sample = DiceSetSample::from_faces([4, 4, 4, 1, 0, 3]).unwrap();
+        assert_eq!(best_selection(sample).faces(), [4, 4, 4, 1, 0, 0]);
+        let sample = DiceSetSample::from_faces([2, 3, 4, 5, 6, 6]).unwrap();
+        assert_eq!(best_selection(sample.clone()).faces(), [2, 3, 4, 5, 0, 6]);
+        for sample in [sample, DiceSetSample::from_faces([5, 1, 1, 5, 0, 2]).unwrap()] {
+            let selection = best_selection(sample.clone());
+            assert_eq!(score(count_sides(&selection.present())), best_score(count_sides(&sample.present())));
+        }
+    }
+
+    #[test]
+    fn breakdown_matches_score() {
+        for h in 0..7usize.pow(6) {
+            let mut occurances = [0u8; 6];
+            let mut n = h;
+            for slot in occurances.iter_mut() {
+                *slot = (n % 7) as u8;
+                n = n / 7;
+            }
+            if occurances.iter().sum::<u8>() > 6 {
+                continue;
+            }
+            let breakdown = score_breakdown(occurances);
+            let used: u32 = breakdown.combinations.iter().map(|(_, sc)| sc).sum();
+            let valid = breakdown.unused == [0; 6];
+            assert_eq!(breakdown.total, if valid { used } else { 0 });
+            assert_eq!(score(occurances).score(), breakdown.total);
+        }
+        let breakdown = score_breakdown(count_sides(&[DiceSide::Two, DiceSide::Two, DiceSide::Two, DiceSide::One, DiceSide::Five]));
+        assert_eq!(breakdown.combinations, vec![
+            (Combination::OfAKind {side: DiceSide::Two, count: 3}, 200),
+            (Combination::SingleOne, 100),
+            (Combination::SingleFive, 50),
+        ]);
+        assert_eq!(breakdown.total, 350);
+    }
+}
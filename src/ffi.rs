@@ -0,0 +1,197 @@
+//! C ABI for embedding the solver in other languages. See include/kcd_farkle_solver.h for the
+//! generated header.
+//!
+//! Encodings used:
+//!  - Dice masks are a byte where bit i is set when die i is present
+//!  - Samples are 6 bytes, one per die, holding the face shown (1 to 6) or 0 when the die is missing
+//!
+//! Every function returning an int gives KCD_OK on success, or a negative error code.
+
+use crate::farkle::{DiceSetSample, FarkleScore};
+use crate::optimal::{MappedStrat, OptimalStrat};
+use std::ffi::{c_char, c_int, CStr};
+use std::fs::File;
+use std::io::BufReader;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::Path;
+
+pub const KCD_OK: c_int = 0;
+/// A required pointer was null
+pub const KCD_NULL_POINTER: c_int = -1;
+/// The score lies outside of the strategy's score domain
+pub const KCD_OUT_OF_RANGE: c_int = -2;
+/// A sample held a byte other than 0 to 6
+pub const KCD_INVALID_SAMPLE: c_int = -3;
+/// Something unexpected went wrong inside the solver
+pub const KCD_INTERNAL_ERROR: c_int = -4;
+
+/// A loaded strategy. Opaque to C.
+pub enum KcdStrategy {
+    Solved(OptimalStrat),
+    Mapped(MappedStrat),
+}
+impl KcdStrategy {
+    fn load(path: &Path) -> Option<Self> {
+        if path.is_dir() {
+            return Some(Self::Mapped(MappedStrat::open(path).ok()?));
+        }
+        let file = File::open(path).ok()?;
+        return Some(Self::Solved(serde_json::from_reader(BufReader::new(file)).ok()?));
+    }
+
+    fn in_domain(&self, score: FarkleScore) -> bool {
+        let domain = match self {
+            Self::Solved(strat) => strat.domain(),
+            Self::Mapped(strat) => strat.domain(),
+        };
+        return domain.index(score).is_some();
+    }
+}
+
+/// Runs the body, turning any panic into KCD_INTERNAL_ERROR so it doesn't unwind into C
+fn guard(body: impl FnOnce() -> c_int) -> c_int {
+    return catch_unwind(AssertUnwindSafe(body)).unwrap_or(KCD_INTERNAL_ERROR);
+}
+
+/// Loads a strategy from either a checkpoint JSON file or a directory of tables written by
+/// OptimalStrat::save_tables(). Returns null on failure.
+///
+/// # Safety
+/// path must be a valid null terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kcd_strategy_load(path: *const c_char) -> *mut KcdStrategy {
+    if path.is_null() {
+        return std::ptr::null_mut();
+    }
+    // SAFETY: Caller guarantees path is a valid null terminated string
+    let path = unsafe { CStr::from_ptr(path) };
+    let Ok(path) = path.to_str() else {
+        return std::ptr::null_mut();
+    };
+    match catch_unwind(|| KcdStrategy::load(Path::new(path))) {
+        Ok(Some(strategy)) => return Box::into_raw(Box::new(strategy)),
+        _ => return std::ptr::null_mut(),
+    }
+}
+
+/// Frees a strategy returned by kcd_strategy_load(). Null is ignored.
+///
+/// # Safety
+/// strategy must have come from kcd_strategy_load() and not already been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kcd_strategy_free(strategy: *mut KcdStrategy) {
+    if !strategy.is_null() {
+        // SAFETY: Caller guarantees the pointer came from Box::into_raw() in kcd_strategy_load()
+        drop(unsafe { Box::from_raw(strategy) });
+    }
+}
+
+/// Writes the expected score gain with the given current score and mask of dice left into out.
+///
+/// # Safety
+/// strategy must be a live strategy and out must be valid to write a float to.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kcd_strategy_query_score(strategy: *const KcdStrategy, score: u32, mask: u8, out: *mut f32) -> c_int {
+    // SAFETY: Caller guarantees the pointers are valid (or null)
+    let (Some(strategy), Some(out)) = (unsafe { strategy.as_ref() }, unsafe { out.as_mut() }) else {
+        return KCD_NULL_POINTER;
+    };
+    return guard(|| {
+        let score = FarkleScore::new(score);
+        if !strategy.in_domain(score) {
+            return KCD_OUT_OF_RANGE;
+        }
+        let dice = std::array::from_fn(|i| mask & (1 << i) != 0);
+        *out = match strategy {
+            KcdStrategy::Solved(strat) => strat.query_score(score, dice),
+            KcdStrategy::Mapped(strat) => strat.query_score(score, dice),
+        };
+        return KCD_OK;
+    });
+}
+
+/// Writes the dice to keep (as a sample) into selection and whether to roll again into roll_again, given
+/// the current score and the sample rolled.
+///
+/// # Safety
+/// strategy must be a live strategy, sample must point to 6 readable bytes, selection to 6 writable bytes
+/// and roll_again must be valid to write a bool to. sample and selection may point to the same array.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kcd_strategy_query_decision(
+    strategy: *const KcdStrategy,
+    score: u32,
+    sample: *const [u8; 6],
+    selection: *mut [u8; 6],
+    roll_again: *mut bool,
+) -> c_int {
+    // SAFETY: Caller guarantees the pointers are valid (or null). The sample is copied out before
+    // selection is borrowed mutably as the two may alias.
+    let Some(faces) = (unsafe { sample.as_ref() }).copied() else {
+        return KCD_NULL_POINTER;
+    };
+    let pointers = unsafe { (strategy.as_ref(), selection.as_mut(), roll_again.as_mut()) };
+    let (Some(strategy), Some(selection), Some(roll_again)) = pointers else {
+        return KCD_NULL_POINTER;
+    };
+    return guard(|| {
+        let score = FarkleScore::new(score);
+        if !strategy.in_domain(score) {
+            return KCD_OUT_OF_RANGE;
+        }
+        let Some(sample) = DiceSetSample::from_faces(faces) else {
+            return KCD_INVALID_SAMPLE;
+        };
+        let (kept, again) = match strategy {
+            KcdStrategy::Solved(strat) => strat.query_decision(score, sample),
+            KcdStrategy::Mapped(strat) => strat.query_decision(score, sample),
+        };
+        *selection = kept.faces();
+        *roll_again = again;
+        return KCD_OK;
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::farkle::{Dice, ScoreDomain};
+    use std::ffi::CString;
+
+    #[test]
+    fn query_through_c_abi() {
+        let dir = std::env::temp_dir().join(format!("kcd_ffi_{}", std::process::id()));
+        let optimal: OptimalStrat = OptimalStrat::with_domain([Dice::default(); 6], ScoreDomain::new(50, 100).unwrap());
+        optimal.save_tables(&dir).unwrap();
+        let checkpoint = dir.join("checkpoint.json");
+        std::fs::write(&checkpoint, serde_json::to_string(&optimal).unwrap()).unwrap();
+
+        for path in [&dir, &checkpoint] {
+            let path = CString::new(path.to_str().unwrap()).unwrap();
+            unsafe {
+                let strategy = kcd_strategy_load(path.as_ptr());
+                assert!(!strategy.is_null());
+
+                let mut out = 0.0;
+                assert_eq!(kcd_strategy_query_score(strategy, 50, 0b000011, &mut out), KCD_OK);
+                assert_eq!(out, optimal.query_score(FarkleScore::new(50), [true, true, false, false, false, false]));
+                assert_eq!(kcd_strategy_query_score(strategy, 100, 0b111111, &mut out), KCD_OUT_OF_RANGE);
+                assert_eq!(kcd_strategy_query_score(strategy, 0, 0, std::ptr::null_mut()), KCD_NULL_POINTER);
+
+                let mut selection = [0u8; 6];
+                let mut roll_again = true;
+                assert_eq!(kcd_strategy_query_decision(strategy, 0, &[1, 2, 3, 0, 5, 5], &mut selection, &mut roll_again), KCD_OK);
+                assert_eq!((selection, roll_again), ([1, 0, 0, 0, 5, 5], false));
+                assert_eq!(kcd_strategy_query_decision(strategy, 0, &[7, 2, 3, 0, 5, 5], &mut selection, &mut roll_again), KCD_INVALID_SAMPLE);
+                // Writing the selection over the sample
+                let mut faces = [1u8, 2, 3, 0, 5, 5];
+                let faces_ptr = &raw mut faces;
+                assert_eq!(kcd_strategy_query_decision(strategy, 0, faces_ptr, faces_ptr, &mut roll_again), KCD_OK);
+                assert_eq!(faces, [1, 0, 0, 0, 5, 5]);
+
+                kcd_strategy_free(strategy);
+            }
+        }
+        assert!(unsafe { kcd_strategy_load(CString::new("/nonexistent").unwrap().as_ptr()) }.is_null());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,172 @@
+//! Tables summarising a solved strategy, written as CSV or Markdown so the findings in the README can
+//! be regenerated rather than edited by hand.
+//!
+//! Values for a number of dice remaining are averaged over every subset of the dice of that size. With
+//! identical dice every such subset is the same, so nothing is lost. Banking thresholds come from the
+//! threshold module, so they are reported per subset.
+
+use crate::farkle::FarkleScore;
+use crate::hash::{PerfectHash, PerfectHashing};
+use crate::optimal::OptimalStrat;
+use crate::threshold::ThresholdReport;
+
+/// Expected score gains, busting probabilities and thresholds of a solved strategy
+#[derive(Clone, Debug, PartialEq)]
+pub struct FindingsReport {
+    /// The n of the Optimal_n strategy summarised
+    pub n: usize,
+    /// Every score tracked by the strategy, in ascending order
+    pub scores: Vec<u32>,
+    /// expected_scores[i][k - 1] is the expected score gain of rolling k dice with a score of scores[i]
+    pub expected_scores: Vec<[f32; 6]>,
+    /// Busting probability of rolling each (non-empty) subset of dice
+    pub bust_prob: Vec<([bool; 6], f32)>,
+    /// Bank thresholds of every (non-empty) subset of dice
+    pub thresholds: ThresholdReport,
+}
+impl FindingsReport {
+    pub fn new(optimal: &OptimalStrat) -> Self {
+        let masks: Vec<[bool; 6]> = (1..<[bool; 6]>::SET_SIZE)
+            .map(|h| <[bool; 6]>::from_perfhash(PerfectHash::new(h)))
+            .collect();
+        let domain = optimal.domain();
+        let scores: Vec<u32> = domain.scores().map(|p| p.score()).collect();
+        let expected_scores: Vec<[f32; 6]> = domain.scores()
+            .map(|p| std::array::from_fn(|k| mean_over_count(masks.iter().map(|&mask| (mask, optimal.query_score(p, mask))), k + 1)))
+            .collect();
+        let thresholds = ThresholdReport::new(optimal);
+        let bust_prob = masks.iter().map(|&mask| (mask, optimal.bust_prob[mask])).collect();
+        return Self {n: optimal.n, scores, expected_scores, bust_prob, thresholds};
+    }
+
+    /// Expected score gain with all 6 dice at the start of a round
+    pub fn round_score(&self) -> f32 {
+        return self.expected_scores[0][5];
+    }
+
+    /// CSV of the expected score gains, one row per score and one column per number of dice remaining
+    pub fn expected_scores_csv(&self) -> String {
+        let mut out = String::from("score,1_dice,2_dice,3_dice,4_dice,5_dice,6_dice\n");
+        for (p, row) in self.scores.iter().zip(self.expected_scores.iter()) {
+            out += &format!("{},{}\n", p, row.map(|v| v.to_string()).join(","));
+        }
+        return out;
+    }
+
+    /// CSV of the busting probabilities, one row per subset of dice. Dice are written as a mask where
+    /// the i-th character is 1 when die i is rolled.
+    pub fn bust_prob_csv(&self) -> String {
+        let mut out = String::from("dice,dice_count,bust_prob\n");
+        for (mask, p) in self.bust_prob.iter() {
+            out += &format!("{},{},{}\n", mask_string(mask), dice_count(mask), p);
+        }
+        return out;
+    }
+
+    /// CSV of the lowest score at which rolling is expected to lose points, one row per subset of dice
+    /// (written the same way as bust_prob_csv()). Left empty when rolling is always worth it.
+    pub fn thresholds_csv(&self) -> String {
+        let mut out = String::from("dice,dice_count,threshold\n");
+        for t in self.thresholds.thresholds.iter() {
+            let threshold = t.threshold.map(|p| p.score().to_string()).unwrap_or_default();
+            out += &format!("{},{},{}\n", mask_string(&t.dice), t.dice_count(), threshold);
+        }
+        return out;
+    }
+
+    /// Markdown of the expected score gains. Only every step-th score is included to keep the table readable.
+    pub fn expected_scores_markdown(&self, step: usize) -> String {
+        let mut out = String::from("| Score | 1 dice | 2 dice | 3 dice | 4 dice | 5 dice | 6 dice |\n");
+        out += "|---:|---:|---:|---:|---:|---:|---:|\n";
+        for (p, row) in self.scores.iter().zip(self.expected_scores.iter()).step_by(step.max(1)) {
+            out += &format!("| {} | {} |\n", p, row.map(|v| format!("{:.2}", v)).join(" | "));
+        }
+        return out;
+    }
+
+    /// Markdown of the busting probability per number of dice rolled
+    pub fn bust_prob_markdown(&self) -> String {
+        let mut out = String::from("| Dice rolled | Bust probability |\n|---:|---:|\n");
+        for k in 1..=6 {
+            let p = mean_over_count(self.bust_prob.iter().copied(), k);
+            out += &format!("| {} | {:.4} |\n", k, p);
+        }
+        return out;
+    }
+
+    /// Markdown of the lowest score at which rolling is expected to lose points, per number of dice remaining.
+    /// Gives the range across subsets when it depends on which dice remain.
+    pub fn thresholds_markdown(&self) -> String {
+        let score = |t: Option<FarkleScore>| t.map(|p| p.score().to_string()).unwrap_or("never".to_string());
+        let mut out = String::from("| Dice remaining | Bank from |\n|---:|---:|\n");
+        for k in 1..=6 {
+            let Some((lowest, highest)) = self.thresholds.range_for_count(k) else {
+                continue;
+            };
+            if lowest == highest {
+                out += &format!("| {} | {} |\n", k, score(lowest));
+            } else {
+                out += &format!("| {} | {} to {} |\n", k, score(lowest), score(highest));
+            }
+        }
+        return out;
+    }
+
+    /// Every Markdown table under headings, ready to paste into the README
+    pub fn markdown(&self, step: usize) -> String {
+        let mut out = format!("For a starting score of 0 with all dice present, Optimal_{} = {:.4}\n\n", self.n, self.round_score());
+        out += "### Expected score gain of rolling\n";
+        out += &self.expected_scores_markdown(step);
+        out += "\n### Busting probability\n";
+        out += &self.bust_prob_markdown();
+        out += "\n### Banking thresholds\n";
+        out += "Lowest score at which rolling the remaining dice is expected to lose points\n\n";
+        out += &self.thresholds_markdown();
+        return out;
+    }
+}
+
+/// Mean of the values of every mask with the given number of dice
+fn mean_over_count(values: impl Iterator<Item = ([bool; 6], f32)>, count: usize) -> f32 {
+    let values: Vec<f32> = values.filter(|(mask, _)| dice_count(mask) == count).map(|(_, v)| v).collect();
+    return values.iter().sum::<f32>() / values.len() as f32;
+}
+
+fn dice_count(mask: &[bool; 6]) -> usize {
+    return mask.iter().filter(|&&b| b).count();
+}
+
+fn mask_string(mask: &[bool; 6]) -> String {
+    return mask.iter().map(|&b| if b { '1' } else { '0' }).collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::farkle::{Dice, ScoreDomain};
+
+    #[test]
+    fn findings_tables() {
+        let optimal: OptimalStrat = OptimalStrat::with_domain([Dice::default(); 6], ScoreDomain::new(50, 1000).unwrap());
+        let report = FindingsReport::new(&optimal);
+        assert_eq!(report.round_score(), optimal.query_score(FarkleScore::new(0), [true; 6]));
+        assert_eq!(report.bust_prob.len(), 63);
+        // Rolling a single die busts 2/3 of the time, so it isn't worth it for long
+        let one = Some(FarkleScore::new(50));
+        assert_eq!(report.thresholds.range_for_count(1), Some((one, one)));
+        // Whereas 6 dice rarely bust
+        assert_eq!(report.thresholds.range_for_count(6), Some((None, None)));
+        assert_eq!(report.thresholds, ThresholdReport::new(&optimal));
+        assert!(report.thresholds_csv().contains("\n100000,1,50\n"));
+        assert!(report.thresholds_csv().contains("\n111111,6,\n"));
+        assert!(report.thresholds_markdown().contains("| 1 | 50 |\n"));
+        assert!(report.thresholds_markdown().ends_with("| 6 | never |\n"));
+
+        let csv = report.expected_scores_csv();
+        assert_eq!(csv.lines().count(), 21);
+        assert!(csv.lines().nth(1).unwrap().starts_with("0,"));
+        assert!(report.bust_prob_csv().contains("\n100000,1,0.6666"));
+        assert_eq!(report.expected_scores_markdown(10).lines().count(), 2 + 2);
+        assert!(report.markdown(10).contains("| 1 | 0.6667 |"));
+    }
+}
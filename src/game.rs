@@ -0,0 +1,223 @@
+//! Engines for playing out rounds and full matches of Farkle between any strategies
+
+use crate::farkle::{count_sides, not_busted, score, Dice, DiceSet, DiceSetSample, FarkleScore};
+use crate::strategy::{Strategy, TurnState};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// How a round came to an end
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RoundEnd {
+    /// The player chose to end the round and keep their score
+    Banked,
+    /// A roll could not form any scoring hand
+    Bust,
+    /// The player selected dice that do not form a valid hand. Treated the same as a bust.
+    InvalidSelection,
+    /// The player reached the target score, winning the game
+    Won,
+}
+
+/// Result of playing out a single round
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct RoundResult {
+    /// Score gained from the round (0 if the round was lost)
+    pub score: u32,
+    /// Number of rolls made during the round
+    pub rolls: usize,
+    /// How the round ended
+    pub end: RoundEnd,
+}
+
+/// A single roll made during a round along with the decision made afterwards
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RollRecord {
+    /// The state of the round just before the decision was made
+    pub state: TurnState,
+    /// What the dice landed on
+    pub roll: DiceSetSample,
+    /// The dice selected to form a hand. Empty if the roll was a bust.
+    pub selection: DiceSetSample,
+    /// Whether the player chose to roll again
+    pub roll_again: bool,
+}
+
+/// Plays out a single round with the given strategy and dice.
+///
+/// The round starts from the given state, which is normally a score of 0 with all 6 die.
+pub fn play_round<S: Strategy + ?Sized, R: Rng + ?Sized>(strategy: &S, dices: &[Dice; 6], state: TurnState, rng: &mut R) -> RoundResult {
+    return play_round_recorded(strategy, dices, state, rng, &mut Vec::new());
+}
+
+/// Same as play_round() but every roll (and decision) made gets pushed onto the given record
+pub fn play_round_recorded<S: Strategy + ?Sized, R: Rng + ?Sized>(
+    strategy: &S,
+    dices: &[Dice; 6],
+    mut state: TurnState,
+    rng: &mut R,
+    record: &mut Vec<RollRecord>,
+) -> RoundResult {
+    let mut rolls = 0;
+    loop {
+        // Having no dice means the player loops back round to 6-dice
+        if state.dice == [false; 6] {
+            state.dice = [true; 6];
+        }
+        let sample = DiceSet::new(dices, state.dice).roll(rng);
+        rolls += 1;
+        if !not_busted(&count_sides(&sample.present())) {
+            record.push(RollRecord {state, roll: sample, selection: DiceSetSample::default(), roll_again: false});
+            return RoundResult {score: 0, rolls, end: RoundEnd::Bust};
+        }
+        let (selection, roll_again) = strategy.decide(&state, &sample);
+        record.push(RollRecord {state, roll: sample.clone(), selection: selection.clone(), roll_again});
+        // Only dice that were actually rolled can be selected
+        let selection_valid = selection.sample.iter()
+            .zip(sample.sample.iter())
+            .all(|(sel, rolled)| sel.is_none() || sel == rolled);
+        let select_score = score(count_sides(&selection.present())).score();
+        if !selection_valid || select_score == 0 {
+            return RoundResult {score: 0, rolls, end: RoundEnd::InvalidSelection};
+        }
+        state.score = FarkleScore::new(state.score.score() + select_score);
+        if state.banked + state.score.score() >= state.target {
+            return RoundResult {score: state.score.score(), rolls, end: RoundEnd::Won};
+        }
+        if !roll_again {
+            return RoundResult {score: state.score.score(), rolls, end: RoundEnd::Banked};
+        }
+        state.dice = DiceSet::new(dices, state.dice).new_subset(&selection.present_mask().map(|b| !b)).select_mask;
+    }
+}
+
+/// Number of rounds (by both players) after which a match is called a draw. Only reached when
+/// neither player is able to reach the target (e.g strategies that never keep any dice).
+pub const MAX_ROUNDS: usize = 10_000;
+
+/// Result of playing out a match between 2 players
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MatchResult {
+    /// Index of the player that won. None if the match was drawn after MAX_ROUNDS.
+    pub winner: Option<usize>,
+    /// Final banked scores of each player
+    pub scores: [u32; 2],
+    /// Number of rounds played in total (by both players)
+    pub rounds: usize,
+}
+
+/// Plays out a match between 2 strategies, each using their own dice. The first player starts.
+///
+/// Players alternate rounds until one of them reaches the target score, or MAX_ROUNDS have been
+/// played.
+pub fn play_match<R: Rng + ?Sized>(
+    players: [&(dyn Strategy + Sync); 2],
+    dices: [&[Dice; 6]; 2],
+    target: u32,
+    rng: &mut R,
+) -> MatchResult {
+    let mut scores = [0u32; 2];
+    let mut rounds = 0;
+    loop {
+        let current = rounds % 2;
+        let state = TurnState {
+            score: FarkleScore::new(0),
+            dice: [true; 6],
+            banked: scores[current],
+            opponent_banked: scores[1 - current],
+            target,
+        };
+        let result = play_round(players[current], dices[current], state, rng);
+        scores[current] += result.score;
+        rounds += 1;
+        if scores[current] >= target {
+            return MatchResult {winner: Some(current), scores, rounds};
+        }
+        if rounds >= MAX_ROUNDS {
+            return MatchResult {winner: None, scores, rounds};
+        }
+    }
+}
+
+/// Derives a well spread seed from a base seed and an index (e.g the position of a match in a
+/// schedule) using splitmix64
+pub fn derive_seed(seed: u64, index: u64) -> u64 {
+    let mut z = seed.wrapping_add(index.wrapping_add(1).wrapping_mul(0x9E3779B97F4A7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    return z ^ (z >> 31);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::farkle::best_score;
+    use crate::heuristic::{KeepRule, ThresholdStrat};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// Never keeps anything
+    struct Forfeit;
+    impl Strategy for Forfeit {
+        fn decide(&self, _state: &TurnState, _sample: &DiceSetSample) -> (DiceSetSample, bool) {
+            return (DiceSetSample::default(), true);
+        }
+    }
+
+    #[test]
+    fn round_ends() {
+        let dices = [Dice::default(); 6];
+        let always_bank = ThresholdStrat::uniform(0, KeepRule::Max);
+        for seed in 0..50 {
+            let mut record = Vec::new();
+            let state = TurnState::new(FarkleScore::new(0), [true; 6]);
+            let result = play_round_recorded(&always_bank, &dices, state, &mut StdRng::seed_from_u64(seed), &mut record);
+            assert_eq!((result.rolls, record.len()), (1, 1));
+            match result.end {
+                RoundEnd::Banked => assert_eq!(result.score, best_score(count_sides(&record[0].roll.present())).score()),
+                RoundEnd::Bust => assert_eq!((result.score, record[0].selection.present().len()), (0, 0)),
+                end => panic!("Unexpected end {:?}", end),
+            }
+            // Same seed, same round
+            let again = play_round(&always_bank, &dices, state, &mut StdRng::seed_from_u64(seed));
+            assert_eq!((again.score, again.end), (result.score, result.end));
+
+            let result = play_round(&Forfeit, &dices, state, &mut StdRng::seed_from_u64(seed));
+            assert!(matches!(result.end, RoundEnd::InvalidSelection | RoundEnd::Bust));
+            assert_eq!(result.score, 0);
+            // Any points at all win the game
+            let state = TurnState {banked: 5990, ..state};
+            let result = play_round(&ThresholdStrat::uniform(u32::MAX, KeepRule::Max), &dices, state, &mut StdRng::seed_from_u64(seed));
+            assert!(matches!(result.end, RoundEnd::Won | RoundEnd::Bust));
+        }
+    }
+
+    #[test]
+    fn match_winner() {
+        let dices = [Dice::default(); 6];
+        let always_bank = ThresholdStrat::uniform(0, KeepRule::Max);
+        let bank_at_300 = ThresholdStrat::uniform(300, KeepRule::Max);
+        let result = play_match([&always_bank, &bank_at_300], [&dices, &dices], 2000, &mut StdRng::seed_from_u64(7));
+        let winner = result.winner.unwrap();
+        assert!(result.scores[winner] >= 2000);
+        assert!(result.scores[1 - winner] < 2000);
+        // The winner played the last round
+        assert_eq!((result.rounds - 1) % 2, winner);
+        let again = play_match([&always_bank, &bank_at_300], [&dices, &dices], 2000, &mut StdRng::seed_from_u64(7));
+        assert_eq!((again.winner, again.scores, again.rounds), (result.winner, result.scores, result.rounds));
+    }
+
+    #[test]
+    fn match_draw() {
+        // Neither player ever scores, so the match can't be won
+        let dices = [Dice::default(); 6];
+        let result = play_match([&Forfeit, &Forfeit], [&dices, &dices], 2000, &mut StdRng::seed_from_u64(7));
+        assert_eq!((result.winner, result.scores, result.rounds), (None, [0, 0], MAX_ROUNDS));
+    }
+
+    #[test]
+    fn derived_seeds() {
+        assert_eq!(derive_seed(1, 2), derive_seed(1, 2));
+        assert_ne!(derive_seed(1, 2), derive_seed(1, 3));
+        assert_ne!(derive_seed(1, 2), derive_seed(2, 2));
+    }
+}
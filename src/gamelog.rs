@@ -0,0 +1,199 @@
+//! Structured logs of played rounds and matches, and replaying them against the optimal strategy
+
+use crate::farkle::{count_sides, not_busted, Dice, DiceSetSample, FarkleScore};
+use crate::game::{derive_seed, play_round_recorded, RollRecord, RoundResult, MAX_ROUNDS};
+use crate::optimal::OptimalStrat;
+use crate::strategy::{Strategy, TurnState};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+
+/// Everything that happened during a single round
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RoundLog {
+    /// Seed of the rng the round was played with. None if the rolls did not come from this crate
+    /// (e.g a round played in the game itself).
+    pub seed: Option<u64>,
+    /// The dice the round was played with
+    pub dices: [Dice; 6],
+    /// State the round started from
+    pub start: TurnState,
+    /// Every roll made in order
+    pub rolls: Vec<RollRecord>,
+    /// How the round ended. None if the round wasn't finished.
+    pub result: Option<RoundResult>,
+}
+impl RoundLog {
+    /// Plays out a round with the given strategy, seeding the rng with the given seed
+    pub fn record<S: Strategy + ?Sized>(strategy: &S, dices: &[Dice; 6], start: TurnState, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut rolls = Vec::new();
+        let result = play_round_recorded(strategy, dices, start, &mut rng, &mut rolls);
+        return Self {seed: Some(seed), dices: *dices, start, rolls, result: Some(result)};
+    }
+
+    /// Plays the round again from the same seed with the given strategy.
+    ///
+    /// Using the same strategy reproduces this log exactly. Returns None if the log has no seed.
+    pub fn rerun<S: Strategy + ?Sized>(&self, strategy: &S) -> Option<Self> {
+        return Some(Self::record(strategy, &self.dices, self.start, self.seed?));
+    }
+
+    /// Annotates every decision made in this round with how much expected score it lost compared to
+    /// the given optimal strategy
+    pub fn annotate(&self, optimal: &OptimalStrat) -> Vec<AnnotatedRoll> {
+        return self.rolls.iter().map(|record| {
+            let (optimal_decision, optimal_value) = optimal.query_best_decision(record.state.score, &record.roll);
+            // Busts have no decision to make. Going by the roll as an empty selection from a roll
+            // that scores is a decision (to give up the score) rather than a bust.
+            if !not_busted(&count_sides(&record.roll.present())) {
+                return AnnotatedRoll {
+                    record: record.clone(),
+                    chosen_value: optimal_value,
+                    optimal_value,
+                    optimal_decision: None,
+                    loss: 0.0,
+                };
+            }
+            let chosen_value = optimal.query_decision_value(record.state.score, &record.roll, &record.selection, record.roll_again);
+            return AnnotatedRoll {
+                record: record.clone(),
+                chosen_value,
+                optimal_value,
+                optimal_decision: Some(optimal_decision),
+                loss: (optimal_value - chosen_value).max(0.0),
+            };
+        }).collect();
+    }
+}
+
+/// A roll and decision from a log, annotated with how it compares to optimal play
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AnnotatedRoll {
+    pub record: RollRecord,
+    /// Expected score gain from this point onwards given the decision that was made
+    pub chosen_value: f32,
+    /// Expected score gain from this point onwards given the best decision
+    pub optimal_value: f32,
+    /// The decision the optimal strategy would have made. None if the roll was a bust.
+    pub optimal_decision: Option<(DiceSetSample, bool)>,
+    /// Expected score lost by the decision made
+    pub loss: f32,
+}
+
+/// Every round played during a match between 2 players
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MatchLog {
+    /// Seed every round's seed was derived from
+    pub seed: u64,
+    /// Score needed to win
+    pub target: u32,
+    /// Rounds in the order they were played. Players alternate rounds with the first player starting.
+    pub rounds: Vec<RoundLog>,
+    /// Index of the player that won. None if the match was drawn after game::MAX_ROUNDS.
+    pub winner: Option<usize>,
+    /// Final banked scores of each player
+    pub scores: [u32; 2],
+}
+impl MatchLog {
+    /// Plays out a match between 2 strategies (same rules as game::play_match), seeding each round
+    /// from the given seed
+    pub fn record(players: [&(dyn Strategy + Sync); 2], dices: [&[Dice; 6]; 2], target: u32, seed: u64) -> Self {
+        let mut scores = [0u32; 2];
+        let mut rounds = Vec::new();
+        loop {
+            let current = rounds.len() % 2;
+            let state = TurnState {
+                score: FarkleScore::new(0),
+                dice: [true; 6],
+                banked: scores[current],
+                opponent_banked: scores[1 - current],
+                target,
+            };
+            let round = RoundLog::record(players[current], dices[current], state, derive_seed(seed, rounds.len() as u64));
+            scores[current] += round.result.map_or(0, |r| r.score);
+            rounds.push(round);
+            if scores[current] >= target {
+                return Self {seed, target, rounds, winner: Some(current), scores};
+            }
+            if rounds.len() >= MAX_ROUNDS {
+                return Self {seed, target, rounds, winner: None, scores};
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::farkle::{best_selection, ScoreDomain};
+    use crate::heuristic::{KeepRule, ThresholdStrat};
+
+    #[test]
+    fn round_log_round_trip() {
+        let heuristic = ThresholdStrat::uniform(300, KeepRule::Max);
+        let start = TurnState::new(FarkleScore::new(0), [true; 6]);
+        for seed in 0..20 {
+            let log = RoundLog::record(&heuristic, &[Dice::default(); 6], start, seed);
+            let json = serde_json::to_string(&log).unwrap();
+            let parsed: RoundLog = serde_json::from_str(&json).unwrap();
+            let rerun = parsed.rerun(&heuristic).unwrap();
+            assert_eq!(serde_json::to_string(&rerun).unwrap(), json);
+            assert_eq!(rerun.rolls.len(), log.result.unwrap().rolls);
+        }
+        let unseeded = RoundLog {seed: None, ..RoundLog::record(&heuristic, &[Dice::default(); 6], start, 0)};
+        assert!(unseeded.rerun(&heuristic).is_none());
+    }
+
+    #[test]
+    fn annotate_against_optimal() {
+        let optimal: OptimalStrat = OptimalStrat::with_domain([Dice::default(); 6], ScoreDomain::new(50, 200).unwrap());
+        let roll = DiceSetSample::from_faces([1, 5, 2, 2, 3, 6]).unwrap();
+        let keep_one = DiceSetSample::from_faces([1, 0, 0, 0, 0, 0]).unwrap();
+        let record = |score: u32, selection: &DiceSetSample, roll_again: bool| RollRecord {
+            state: TurnState::new(FarkleScore::new(score), [true; 6]),
+            roll: roll.clone(),
+            selection: selection.clone(),
+            roll_again,
+        };
+        let bust = RollRecord {roll: DiceSetSample::from_faces([2, 2, 3, 4, 6, 6]).unwrap(), selection: DiceSetSample::default(), ..record(100, &keep_one, false)};
+        let log = RoundLog {
+            seed: None,
+            dices: [Dice::default(); 6],
+            start: TurnState::new(FarkleScore::new(0), [true; 6]),
+            // Rolling on from 150 goes past the cap of 200, which ends the round
+            rolls: vec![record(0, &best_selection(roll.clone()), false), record(150, &keep_one, true), bust],
+            result: None,
+        };
+        let annotated = log.annotate(&optimal);
+        assert_eq!(annotated[0].chosen_value, 150.0);
+        assert_eq!(annotated[1].chosen_value, 100.0);
+        assert_eq!(annotated[1].loss, annotated[1].optimal_value - 100.0);
+        assert!(annotated.iter().all(|a| a.loss >= 0.0));
+        assert_eq!((annotated[2].optimal_decision.clone(), annotated[2].loss, annotated[2].chosen_value), (None, 0.0, -100.0));
+
+        // Keeping nothing from a roll that scores loses the turn score like a bust, but could have been avoided
+        let log = RoundLog {rolls: vec![record(100, &DiceSetSample::default(), false)], ..log};
+        let annotated = &log.annotate(&optimal)[0];
+        assert_eq!(annotated.chosen_value, -100.0);
+        assert!(annotated.optimal_decision.is_some());
+        assert_eq!(annotated.loss, annotated.optimal_value + 100.0);
+    }
+
+    #[test]
+    fn match_log_round_trip() {
+        let players: [&(dyn Strategy + Sync); 2] = [&ThresholdStrat::uniform(0, KeepRule::Max), &ThresholdStrat::uniform(300, KeepRule::Max)];
+        let dices = [Dice::default(); 6];
+        let log = MatchLog::record(players, [&dices, &dices], 1000, 11);
+        let winner = log.winner.unwrap();
+        assert!(log.scores[winner] >= 1000);
+        assert_eq!((log.rounds.len() - 1) % 2, winner);
+        let parsed: MatchLog = serde_json::from_str(&serde_json::to_string(&log).unwrap()).unwrap();
+        for (i, round) in parsed.rounds.iter().enumerate() {
+            let rerun = round.rerun(players[i % 2]).unwrap();
+            assert_eq!(serde_json::to_string(&rerun).unwrap(), serde_json::to_string(&log.rounds[i]).unwrap());
+        }
+        let total: u32 = parsed.rounds.iter().step_by(2).map(|r| r.result.unwrap().score).sum();
+        assert_eq!(total, log.scores[0]);
+    }
+}
@@ -1,21 +1,33 @@
 //! Traits and structs for objects that can be perfectly hashed
 
+use std::collections::BTreeMap;
+use std::fmt;
 use std::marker::PhantomData;
 use serde::{Deserialize, Serialize};
 
-use crate::farkle::{DiceSide, FarkleScore, DiceSetSample};
+use crate::farkle::{FarkleScore, ScoreDomain, DEFAULT_CAP, DEFAULT_GRANULARITY};
 use std::ops::{Index, IndexMut};
 
+/// Derives PerfectHashing for fieldless enums and structs of perfectly hashable fields
+/// (including fixed size arrays and Options of them)
+pub use kcd_farkle_solver_derive::PerfectHashing;
+
 /// The associated Hash type from a struct that has implemented PerfectHashing trait
-#[derive(Copy, Clone)]
 pub struct PerfectHash<T> {
     hash: usize,
     associated: PhantomData<T>,
 }
+// Implemented by hand as deriving would require T to be Copy as well
+impl<T> Clone for PerfectHash<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T> Copy for PerfectHash<T> {}
 impl<T> PerfectHash<T> {
     pub fn new(hash: usize) -> Self {
         return Self {
-            hash: hash,
+            hash,
             associated: PhantomData,
         }
     }
@@ -28,6 +40,15 @@ impl<T> From<PerfectHash<T>> for usize {
     }
 }
 
+/// Multiplies two set sizes together. Used when combining hashes so a SET_SIZE that overflows usize
+/// fails at compile time (when the constant is evaluated) rather than wrapping around.
+pub const fn checked_set_size(a: usize, b: usize) -> usize {
+    match a.checked_mul(b) {
+        Some(n) => return n,
+        None => panic!("SET_SIZE overflows usize"),
+    }
+}
+
 /// Implementing perfect hashing capabilities on a set of objects a struct may represent.
 pub trait PerfectHashing: Sized {
     /// The size of the set of the object the hashing will operate on.
@@ -36,108 +57,416 @@ pub trait PerfectHashing: Sized {
 
     fn to_perfhash(&self) -> PerfectHash<Self>;
     fn from_perfhash(hash: PerfectHash<Self>) -> Self;
+
+    /// Same as to_perfhash() but returns None if this object lies outside the set being hashed
+    /// (e.g a FarkleScore beyond the maximum score)
+    fn try_to_perfhash(&self) -> Option<PerfectHash<Self>> {
+        let hash = self.to_perfhash();
+        if usize::from(hash) >= Self::SET_SIZE {
+            return None;
+        }
+        return Some(hash);
+    }
+    /// Same as from_perfhash() but returns None for hashes that are out of range
+    fn try_from_perfhash(hash: PerfectHash<Self>) -> Option<Self> {
+        if usize::from(hash) >= Self::SET_SIZE {
+            return None;
+        }
+        return Some(Self::from_perfhash(hash));
+    }
 }
 
-/// A Hashmap where a perfect hashing function exists for the keys
+/// Storage for the values of a PerfectHashMap, addressed by hash
+pub trait Backend<V>: Sized {
+    /// Creates storage for hashes 0 to size-1 where every value starts as the default
+    fn with_size(size: usize) -> Self;
+    /// Number of hashes the storage covers
+    fn size(&self) -> usize;
+    /// Returns the value stored for the hash. None if the hash is out of range.
+    fn get(&self, hash: usize) -> Option<&V>;
+    /// Returns the value stored for the hash. None if the hash is out of range.
+    fn get_mut(&mut self, hash: usize) -> Option<&mut V>;
+    /// Iterates through the value of every hash in order of hash
+    fn iter<'a>(&'a self) -> impl ExactSizeIterator<Item = (usize, &'a V)> where V: 'a;
+    /// Iterates through the value of every hash in order of hash
+    fn iter_mut<'a>(&'a mut self) -> impl ExactSizeIterator<Item = (usize, &'a mut V)> where V: 'a;
+}
+
+/// Backend where every hash has a value allocated upfront. Best when most keys get used.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PerfectHashMap<K: PerfectHashing, V: Sized + Default> {
-    map: Vec<V>,
-    associated: PhantomData<K>,
+#[serde(transparent)]
+pub struct Dense<V> {
+    values: Vec<V>,
 }
-impl<K: PerfectHashing, V: Sized + Default> PerfectHashMap<K, V> {
-    pub fn new() -> Self {
-        let mut vec = Vec::with_capacity(K::SET_SIZE);
-        for _ in 0..K::SET_SIZE {
-            vec.push(V::default());
+impl<V: Default> Backend<V> for Dense<V> {
+    fn with_size(size: usize) -> Self {
+        return Self {values: default_values(size)};
+    }
+    fn size(&self) -> usize {
+        return self.values.len();
+    }
+    fn get(&self, hash: usize) -> Option<&V> {
+        return self.values.get(hash);
+    }
+    fn get_mut(&mut self, hash: usize) -> Option<&mut V> {
+        return self.values.get_mut(hash);
+    }
+    fn iter<'a>(&'a self) -> impl ExactSizeIterator<Item = (usize, &'a V)> where V: 'a {
+        return self.values.iter().enumerate();
+    }
+    fn iter_mut<'a>(&'a mut self) -> impl ExactSizeIterator<Item = (usize, &'a mut V)> where V: 'a {
+        return self.values.iter_mut().enumerate();
+    }
+}
+
+/// Backend where only values that have been written to take up memory. Best for key spaces where most
+/// keys are never reached.
+///
+/// Reading an unwritten (but in range) key gives the default value, and iter() gives the default for every
+/// unwritten key. As iter_mut() hands out every value mutably, it fills in the unwritten keys first so is
+/// only worth calling when most keys are going to be written anyway.
+///
+/// Deserialising fails if a key lies outside of the size.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "SparseValues<V>", bound(deserialize = "V: Default + Deserialize<'de>"))]
+pub struct Sparse<V> {
+    size: usize,
+    values: BTreeMap<usize, V>,
+    #[serde(skip)]
+    default: V,
+}
+impl<V: Default> Backend<V> for Sparse<V> {
+    fn with_size(size: usize) -> Self {
+        return Self {size, values: BTreeMap::new(), default: V::default()};
+    }
+    fn size(&self) -> usize {
+        return self.size;
+    }
+    fn get(&self, hash: usize) -> Option<&V> {
+        if hash >= self.size {
+            return None;
+        }
+        return Some(self.values.get(&hash).unwrap_or(&self.default));
+    }
+    fn get_mut(&mut self, hash: usize) -> Option<&mut V> {
+        if hash >= self.size {
+            return None;
+        }
+        return Some(self.values.entry(hash).or_default());
+    }
+    fn iter<'a>(&'a self) -> impl ExactSizeIterator<Item = (usize, &'a V)> where V: 'a {
+        return (0..self.size).map(|h| (h, self.values.get(&h).unwrap_or(&self.default)));
+    }
+    fn iter_mut<'a>(&'a mut self) -> impl ExactSizeIterator<Item = (usize, &'a mut V)> where V: 'a {
+        for h in 0..self.size {
+            self.values.entry(h).or_default();
         }
+        return self.values.iter_mut().map(|(&h, v)| (h, v));
+    }
+}
+
+/// Serialised form of a Sparse backend, validated when converting into one
+#[derive(Deserialize)]
+struct SparseValues<V> {
+    size: usize,
+    values: BTreeMap<usize, V>,
+}
+impl<V: Default> TryFrom<SparseValues<V>> for Sparse<V> {
+    type Error = MapError;
+
+    fn try_from(value: SparseValues<V>) -> Result<Self, Self::Error> {
+        // Keys are sorted so only the largest needs checking
+        if let Some(&key) = value.values.keys().next_back().filter(|&&key| key >= value.size) {
+            return Err(MapError::KeyOutOfRange {key, size: value.size});
+        }
+        return Ok(Self {size: value.size, values: value.values, default: V::default()});
+    }
+}
+
+/// A Hashmap where a perfect hashing function exists for the keys.
+///
+/// Values are kept in a Dense backend by default. Use the Sparse backend for key spaces that are
+/// mostly never reached.
+///
+/// Deserialising fails unless the backend holds exactly K::SET_SIZE values.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "MapValues<B>", bound(deserialize = "B: Deserialize<'de>"))]
+pub struct PerfectHashMap<K: PerfectHashing, V: Sized + Default, B: Backend<V> = Dense<V>> {
+    map: B,
+    associated: PhantomData<(K, V)>,
+}
+impl<K: PerfectHashing, V: Sized + Default, B: Backend<V>> PerfectHashMap<K, V, B> {
+    pub fn new() -> Self {
         return Self {
-            map: vec,
+            map: B::with_size(K::SET_SIZE),
             associated: PhantomData,
         }
     }
 
+    /// Returns the value for the key. None if the key lies outside of the set being hashed.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        return self.map.get(usize::from(key.try_to_perfhash()?));
+    }
+
+    /// Returns the value for the key. None if the key lies outside of the set being hashed.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        return self.map.get_mut(usize::from(key.try_to_perfhash()?));
+    }
+
     pub fn iter(&self) -> impl ExactSizeIterator<Item = (K, &V)> {
-        return (0..K::SET_SIZE).into_iter()
-            .zip(self.map.iter())
+        return self.map.iter()
             .map(|(k, v)| (K::from_perfhash(PerfectHash::new(k)), v));
     }
 
     pub fn iter_mut(&mut self) -> impl ExactSizeIterator<Item = (K, &mut V)> {
-        return (0..K::SET_SIZE).into_iter()
-            .zip(self.map.iter_mut())
+        return self.map.iter_mut()
             .map(|(k, v)| (K::from_perfhash(PerfectHash::new(k)), v));
     }
 }
-impl<K: PerfectHashing, V: Sized + Default> Index<K> for PerfectHashMap<K, V> {
+/// Serialised form of a PerfectHashMap, validated when converting into one
+#[derive(Deserialize)]
+struct MapValues<B> {
+    map: B,
+}
+impl<K: PerfectHashing, V: Sized + Default, B: Backend<V>> TryFrom<MapValues<B>> for PerfectHashMap<K, V, B> {
+    type Error = MapError;
+
+    fn try_from(value: MapValues<B>) -> Result<Self, Self::Error> {
+        if value.map.size() != K::SET_SIZE {
+            return Err(MapError::WrongSize {expected: K::SET_SIZE, found: value.map.size()});
+        }
+        return Ok(Self {map: value.map, associated: PhantomData});
+    }
+}
+impl<K: PerfectHashing, V: Sized + Default, B: Backend<V>> Default for PerfectHashMap<K, V, B> {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+impl<K: PerfectHashing, V: Sized + Default, B: Backend<V>> Index<K> for PerfectHashMap<K, V, B> {
     type Output = V;
     
     fn index(&self, index: K) -> &Self::Output {
-        return &self.map[usize::from(index.to_perfhash())]
+        return self.get(&index).expect("Key lies outside of the perfectly hashed set")
     }
 }
-impl<K: PerfectHashing, V: Sized + Default> IndexMut<K> for PerfectHashMap<K, V> {
+impl<K: PerfectHashing, V: Sized + Default, B: Backend<V>> IndexMut<K> for PerfectHashMap<K, V, B> {
     fn index_mut(&mut self, index: K) -> &mut Self::Output {
-        return &mut self.map[usize::from(index.to_perfhash())]
+        return self.get_mut(&index).expect("Key lies outside of the perfectly hashed set")
+    }
+}
+
+
+/// A Hashmap keyed by a score along with a perfectly hashable key, where the range of scores is given by
+/// a ScoreDomain at runtime rather than a constant SET_SIZE.
+///
+/// Keys are laid out the same as the tuple (FarkleScore, K), so over the default domain this map stores
+/// values in the same order as a PerfectHashMap<(FarkleScore, K), V>.
+///
+/// Deserialising fails unless there is a value for every key of the domain.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "ScoreMapValues<V>", bound(deserialize = "V: Deserialize<'de>"))]
+pub struct ScoreMap<K: PerfectHashing, V: Sized + Default> {
+    map: Vec<V>,
+    /// Maps saved before the domain was configurable always used the default domain
+    #[serde(default)]
+    domain: ScoreDomain,
+    #[serde(skip)]
+    associated: PhantomData<K>,
+}
+impl<K: PerfectHashing, V: Sized + Default> ScoreMap<K, V> {
+    pub fn new(domain: ScoreDomain) -> Self {
+        return Self {
+            map: default_values(domain.len() * K::SET_SIZE),
+            domain,
+            associated: PhantomData,
+        }
+    }
+
+    /// The range of scores this map covers
+    pub fn domain(&self) -> ScoreDomain {
+        return self.domain;
+    }
+
+    /// Total number of keys
+    pub fn set_size(&self) -> usize {
+        return self.domain.len() * K::SET_SIZE;
+    }
+
+    /// Returns the hash of the key. None if the key lies outside of the set being hashed.
+    pub fn hash(&self, key: &(FarkleScore, K)) -> Option<usize> {
+        return Some(self.domain.index(key.0)? * K::SET_SIZE + usize::from(key.1.try_to_perfhash()?));
+    }
+
+    /// Recovers the key from its hash
+    pub fn key(&self, hash: usize) -> (FarkleScore, K) {
+        return score_key(self.domain, hash);
+    }
+
+    /// Returns the value for the key. None if the key lies outside of the set being hashed.
+    pub fn get(&self, key: &(FarkleScore, K)) -> Option<&V> {
+        return self.map.get(self.hash(key)?);
+    }
+
+    /// Returns the value for the key. None if the key lies outside of the set being hashed.
+    pub fn get_mut(&mut self, key: &(FarkleScore, K)) -> Option<&mut V> {
+        let hash = self.hash(key)?;
+        return self.map.get_mut(hash);
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = ((FarkleScore, K), &V)> {
+        return self.map.iter()
+            .enumerate()
+            .map(|(k, v)| (self.key(k), v));
+    }
+
+    pub fn iter_mut(&mut self) -> impl ExactSizeIterator<Item = ((FarkleScore, K), &mut V)> {
+        let domain = self.domain;
+        return self.map.iter_mut()
+            .enumerate()
+            .map(move |(k, v)| (score_key(domain, k), v));
+    }
+}
+/// Serialised form of a ScoreMap, validated when converting into one
+#[derive(Deserialize)]
+struct ScoreMapValues<V> {
+    map: Vec<V>,
+    /// Maps saved before the domain was configurable always used the default domain
+    #[serde(default)]
+    domain: ScoreDomain,
+}
+impl<K: PerfectHashing, V: Sized + Default> TryFrom<ScoreMapValues<V>> for ScoreMap<K, V> {
+    type Error = MapError;
+
+    fn try_from(value: ScoreMapValues<V>) -> Result<Self, Self::Error> {
+        let expected = value.domain.len() * K::SET_SIZE;
+        if value.map.len() != expected {
+            return Err(MapError::WrongSize {expected, found: value.map.len()});
+        }
+        return Ok(Self {map: value.map, domain: value.domain, associated: PhantomData});
+    }
+}
+
+/// Reasons serialised map data can't be loaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MapError {
+    /// The number of values doesn't match the number of keys
+    WrongSize {expected: usize, found: usize},
+    /// A Sparse backend holds a value for a key outside of its size
+    KeyOutOfRange {key: usize, size: usize},
+}
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongSize {expected, found} => return write!(f, "expected a map of {} keys but found {}", expected, found),
+            Self::KeyOutOfRange {key, size} => return write!(f, "map key {} lies outside of the size {}", key, size),
+        }
+    }
+}
+impl std::error::Error for MapError {}
+
+/// Creates size default values
+fn default_values<V: Default>(size: usize) -> Vec<V> {
+    let mut values = Vec::with_capacity(size);
+    for _ in 0..size {
+        values.push(V::default());
     }
+    return values;
 }
 
+/// Inverse of ScoreMap::hash()
+fn score_key<K: PerfectHashing>(domain: ScoreDomain, hash: usize) -> (FarkleScore, K) {
+    return (domain.score(hash / K::SET_SIZE), K::from_perfhash(PerfectHash::new(hash % K::SET_SIZE)));
+}
+impl<K: PerfectHashing, V: Sized + Default> Index<(FarkleScore, K)> for ScoreMap<K, V> {
+    type Output = V;
 
-impl PerfectHashing for DiceSetSample {
-    const SET_SIZE: usize = 7usize.pow(6);
+    fn index(&self, index: (FarkleScore, K)) -> &Self::Output {
+        return self.get(&index).expect("Key lies outside of the perfectly hashed set")
+    }
+}
+impl<K: PerfectHashing, V: Sized + Default> IndexMut<(FarkleScore, K)> for ScoreMap<K, V> {
+    fn index_mut(&mut self, index: (FarkleScore, K)) -> &mut Self::Output {
+        return self.get_mut(&index).expect("Key lies outside of the perfectly hashed set")
+    }
+}
+
+impl PerfectHashing for bool {
+    const SET_SIZE: usize = 2;
 
     fn to_perfhash(&self) -> PerfectHash<Self> {
-        let mut val = 0;
-        for (i, dice_outcome) in self.sample.iter().enumerate() {
-            let n;
-            if let &Some(side) = dice_outcome {
-                n = (side as usize) + 1;
-            } else {
-                n = 0;
-            }
-            val += n * 7usize.pow(i as u32);
+        return PerfectHash::new(*self as usize);
+    }
+    fn from_perfhash(hash: PerfectHash<Self>) -> Self {
+        return usize::from(hash) != 0;
+    }
+}
+
+/// None hashes to 0 and Some(x) hashes to 1 + hash(x)
+impl<T: PerfectHashing> PerfectHashing for Option<T> {
+    const SET_SIZE: usize = T::SET_SIZE + 1;
+
+    fn to_perfhash(&self) -> PerfectHash<Self> {
+        match self {
+            None => return PerfectHash::new(0),
+            Some(inner) => return PerfectHash::new(1 + usize::from(inner.to_perfhash())),
         }
-        return PerfectHash::new(val);
     }
     fn from_perfhash(hash: PerfectHash<Self>) -> Self {
-        let mut n: usize = hash.into();
-        let mut output = [None; 6];
-        for slot in output.iter_mut() {
-            let i = n % 7;
-            n = n / 7;
-            match i {
-                0 => *slot = None,
-                _ => *slot = Some(DiceSide::from(i as u8)),
-            }
+        match usize::from(hash) {
+            0 => return None,
+            n => return Some(T::from_perfhash(PerfectHash::new(n - 1))),
+        }
+    }
+    fn try_to_perfhash(&self) -> Option<PerfectHash<Self>> {
+        match self {
+            None => return Some(PerfectHash::new(0)),
+            Some(inner) => return Some(PerfectHash::new(1 + usize::from(inner.try_to_perfhash()?))),
         }
-        return Self::new(output);
     }
 }
 
-impl PerfectHashing for [bool; 6] {
-    const SET_SIZE: usize = 2usize.pow(6);
+/// Elements are combined with the first element being the least significant
+impl<T: PerfectHashing, const N: usize> PerfectHashing for [T; N] {
+    const SET_SIZE: usize = {
+        let mut size = 1;
+        let mut i = 0;
+        while i < N {
+            size = checked_set_size(size, T::SET_SIZE);
+            i += 1;
+        }
+        size
+    };
 
     fn to_perfhash(&self) -> PerfectHash<Self> {
         let num = self.iter()
-            .enumerate()
-            .fold(0, |acc, (i, bit)| {
-                acc + (*bit as usize) * 2usize.pow(i as u32)
+            .rev()
+            .fold(0, |acc, elem| {
+                acc * T::SET_SIZE + usize::from(elem.to_perfhash())
             });
         return PerfectHash::new(num);
     }
     fn from_perfhash(hash: PerfectHash<Self>) -> Self {
         let mut num: usize = hash.into();
-        let mut mask: [bool; 6] = [false; 6];
-        for i in 0..6 {
-            mask[i] = (num % 2) != 0;
-            num = num / 2
+        return std::array::from_fn(|_| {
+            let elem = T::from_perfhash(PerfectHash::new(num % T::SET_SIZE));
+            num = num / T::SET_SIZE;
+            return elem;
+        });
+    }
+    fn try_to_perfhash(&self) -> Option<PerfectHash<Self>> {
+        let mut num = 0;
+        for elem in self.iter().rev() {
+            num = num * T::SET_SIZE + usize::from(elem.try_to_perfhash()?);
         }
-        return mask;
+        return Some(PerfectHash::new(num));
     }
 }
 
-const HASH_DIV: usize = 50;
+const HASH_DIV: usize = DEFAULT_GRANULARITY as usize;
+/// Hashes scores of the default ScoreDomain. Use a ScoreMap for any other domain.
 impl PerfectHashing for FarkleScore {
-    const SET_SIZE: usize = 6000 / HASH_DIV;
+    const SET_SIZE: usize = DEFAULT_CAP as usize / HASH_DIV;
 
     fn to_perfhash(&self) -> PerfectHash<Self> {
         return PerfectHash::new(self.value as usize / HASH_DIV);
@@ -145,18 +474,282 @@ impl PerfectHashing for FarkleScore {
     fn from_perfhash(hash: PerfectHash<Self>) -> Self {
         return Self::new((usize::from(hash) * HASH_DIV) as u32);
     }
+    /// Scores that aren't a multiple of HASH_DIV can't be represented either
+    fn try_to_perfhash(&self) -> Option<PerfectHash<Self>> {
+        if self.value as usize % HASH_DIV != 0 || self.value as usize / HASH_DIV >= Self::SET_SIZE {
+            return None;
+        }
+        return Some(self.to_perfhash());
+    }
 }
 
-impl<T1: PerfectHashing, T2: PerfectHashing> PerfectHashing for (T1, T2) {
-    const SET_SIZE: usize = T1::SET_SIZE * T2::SET_SIZE;
+/// Implements PerfectHashing for tuples. Elements are combined with the first element being the most significant.
+macro_rules! impl_tuple {
+    ($($T:ident $i:tt),+) => {
+        impl<$($T: PerfectHashing),+> PerfectHashing for ($($T,)+) {
+            const SET_SIZE: usize = {
+                let size = 1;
+                $(let size = checked_set_size(size, $T::SET_SIZE);)+
+                size
+            };
 
-    fn to_perfhash(&self) -> PerfectHash<Self> {
-        return PerfectHash::new(usize::from(self.0.to_perfhash()) * T2::SET_SIZE + usize::from(self.1.to_perfhash()));
+            fn to_perfhash(&self) -> PerfectHash<Self> {
+                let mut hash = 0;
+                $(hash = hash * $T::SET_SIZE + usize::from(self.$i.to_perfhash());)+
+                return PerfectHash::new(hash);
+            }
+            fn from_perfhash(hash: PerfectHash<Self>) -> Self {
+                let n: usize = hash.into();
+                // Weight of the element currently being decoded
+                let mut weight = Self::SET_SIZE;
+                return ($({
+                    weight = weight / $T::SET_SIZE;
+                    $T::from_perfhash(PerfectHash::new((n / weight) % $T::SET_SIZE))
+                },)+);
+            }
+            fn try_to_perfhash(&self) -> Option<PerfectHash<Self>> {
+                let mut hash = 0;
+                $(hash = hash * $T::SET_SIZE + usize::from(self.$i.try_to_perfhash()?);)+
+                return Some(PerfectHash::new(hash));
+            }
+        }
+    };
+}
+impl_tuple!(T1 0);
+impl_tuple!(T1 0, T2 1);
+impl_tuple!(T1 0, T2 1, T3 2);
+impl_tuple!(T1 0, T2 1, T3 2, T4 3);
+impl_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4);
+impl_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::farkle::{DiceSetSample, DiceSide, ScoreDomainError};
+    use std::fmt::Debug;
+
+    /// Checks hashing is a bijection between every value of T and 0..SET_SIZE
+    fn assert_round_trip<T: PerfectHashing + PartialEq + Debug>() {
+        for h in 0..T::SET_SIZE {
+            let value = T::from_perfhash(PerfectHash::new(h));
+            assert_eq!(usize::from(value.to_perfhash()), h, "{:?} does not hash back to {}", value, h);
+            assert_eq!(T::from_perfhash(value.to_perfhash()), value);
+        }
     }
-    fn from_perfhash(hash: PerfectHash<Self>) -> Self {
-        let n: usize = hash.into();
-        let t2 = T2::from_perfhash(PerfectHash::new(n % T2::SET_SIZE));
-        let t1 = T1::from_perfhash(PerfectHash::new(n / T2::SET_SIZE));
-        return (t1, t2);
+
+    #[test]
+    fn bool_round_trip() {
+        assert_eq!(bool::SET_SIZE, 2);
+        assert_round_trip::<bool>();
+    }
+
+    #[test]
+    fn dice_side_round_trip() {
+        assert_eq!(DiceSide::SET_SIZE, 6);
+        assert_round_trip::<DiceSide>();
+    }
+
+    #[test]
+    fn option_round_trip() {
+        assert_eq!(<Option<DiceSide>>::SET_SIZE, 7);
+        assert_round_trip::<Option<DiceSide>>();
+        assert_round_trip::<Option<Option<bool>>>();
+    }
+
+    #[test]
+    fn array_round_trip() {
+        assert_eq!(<[bool; 6]>::SET_SIZE, 64);
+        assert_round_trip::<[bool; 6]>();
+        assert_round_trip::<[Option<DiceSide>; 6]>();
+        assert_round_trip::<[DiceSide; 0]>();
+    }
+
+    #[test]
+    fn array_layout() {
+        // First element is the least significant
+        assert_eq!(usize::from([true, false, false, false, false, false].to_perfhash()), 1);
+        assert_eq!(usize::from([false, false, false, false, false, true].to_perfhash()), 32);
+    }
+
+    #[test]
+    fn dice_set_sample_round_trip() {
+        assert_eq!(DiceSetSample::SET_SIZE, 7usize.pow(6));
+        assert_round_trip::<DiceSetSample>();
+    }
+
+    #[test]
+    fn dice_set_sample_layout() {
+        // Saved hold tables depend on this staying the same (see optimal::CHECKPOINT_FORMAT). Missing
+        // dice hash to 0 and sides to their face, with the first die the least significant.
+        let hash = |faces: [u8; 6]| usize::from(DiceSetSample::from_faces(faces).unwrap().to_perfhash());
+        assert_eq!(hash([0; 6]), 0);
+        assert_eq!(hash([1, 0, 0, 0, 0, 0]), 1);
+        assert_eq!(hash([6, 0, 0, 0, 0, 0]), 6);
+        assert_eq!(hash([0, 1, 0, 0, 0, 0]), 7);
+        assert_eq!(hash([2, 0, 0, 0, 0, 3]), 2 + 3 * 7usize.pow(5));
+        assert_eq!(hash([6; 6]), DiceSetSample::SET_SIZE - 1);
+        assert_eq!(DiceSetSample::from_perfhash(PerfectHash::new(1)).faces(), [1, 0, 0, 0, 0, 0]);
+        assert_eq!(DiceSetSample::from_perfhash(PerfectHash::new(2 + 3 * 7usize.pow(5))).faces(), [2, 0, 0, 0, 0, 3]);
+    }
+
+    #[test]
+    fn farkle_score_round_trip() {
+        assert_round_trip::<FarkleScore>();
+        assert_round_trip::<(FarkleScore, [bool; 6])>();
+    }
+
+    #[test]
+    fn tuple_round_trip() {
+        assert_round_trip::<(DiceSide,)>();
+        assert_round_trip::<(bool, DiceSide)>();
+        assert_round_trip::<(DiceSide, bool, Option<bool>)>();
+        assert_round_trip::<(bool, DiceSide, bool, Option<DiceSide>)>();
+        assert_round_trip::<(bool, bool, DiceSide, bool, Option<bool>)>();
+        assert_round_trip::<(DiceSide, bool, bool, Option<DiceSide>, bool, DiceSide)>();
+    }
+
+    #[test]
+    fn out_of_range_keys() {
+        assert!(FarkleScore::new(6000).try_to_perfhash().is_none());
+        assert!(FarkleScore::new(75).try_to_perfhash().is_none());
+        assert!((FarkleScore::new(6000), [true; 6]).try_to_perfhash().is_none());
+        assert!(([true; 6], FarkleScore::new(6000)).try_to_perfhash().is_none());
+        assert!([Some(FarkleScore::new(6050))].try_to_perfhash().is_none());
+        assert!(DiceSide::try_from_perfhash(PerfectHash::new(6)).is_none());
+        assert!(<(bool, DiceSide)>::try_from_perfhash(PerfectHash::new(12)).is_none());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn map_get() {
+        let mut map = PerfectHashMap::<(FarkleScore, [bool; 6]), f32>::new();
+        *map.get_mut(&(FarkleScore::new(100), [true; 6])).unwrap() = 1.0;
+        assert_eq!(map.get(&(FarkleScore::new(100), [true; 6])), Some(&1.0));
+        assert_eq!(map.get(&(FarkleScore::new(150), [true; 6])), Some(&0.0));
+        assert_eq!(map.get(&(FarkleScore::new(6000), [true; 6])), None);
+        assert!(map.get_mut(&(FarkleScore::new(6000), [true; 6])).is_none());
+        assert_eq!(map.iter().count(), <(FarkleScore, [bool; 6])>::SET_SIZE);
+    }
+
+    #[test]
+    fn sparse_map() {
+        // Key spaces this large only take up memory for the keys written
+        let mut large = PerfectHashMap::<(FarkleScore, DiceSetSample), f32, Sparse<f32>>::new();
+        let key = (FarkleScore::new(5950), DiceSetSample::from_faces([1, 0, 5, 0, 0, 0]).unwrap());
+        large[key.clone()] = 2.0;
+        assert_eq!(large.get(&key), Some(&2.0));
+        assert_eq!(large.get(&(FarkleScore::new(0), DiceSetSample::default())), Some(&0.0));
+        assert_eq!(large.get(&(FarkleScore::new(6000), DiceSetSample::default())), None);
+
+        let mut dense = PerfectHashMap::<(FarkleScore, [bool; 6]), f32>::new();
+        let mut sparse = PerfectHashMap::<(FarkleScore, [bool; 6]), f32, Sparse<f32>>::new();
+        dense[(FarkleScore::new(100), [true; 6])] = 1.0;
+        sparse[(FarkleScore::new(100), [true; 6])] = 1.0;
+        assert_eq!(sparse.get(&(FarkleScore::new(150), [true; 6])), Some(&0.0));
+        assert!(sparse.get_mut(&(FarkleScore::new(6000), [true; 6])).is_none());
+        // Every key is visited, with the default for those never written
+        assert_eq!(sparse.iter().len(), <(FarkleScore, [bool; 6])>::SET_SIZE);
+        assert!(sparse.iter().zip(dense.iter()).all(|((a, x), (b, y))| a == b && x == y));
+        for ((_, x), (_, y)) in sparse.iter_mut().zip(dense.iter_mut()) {
+            *x += 0.5;
+            *y += 0.5;
+        }
+        assert_eq!(sparse.iter_mut().len(), <(FarkleScore, [bool; 6])>::SET_SIZE);
+        assert!(sparse.iter().zip(dense.iter()).all(|((a, x), (b, y))| a == b && x == y));
+        assert_eq!(sparse[(FarkleScore::new(100), [true; 6])], 1.5);
+    }
+
+    #[test]
+    fn tuple_layout() {
+        // First element is the most significant
+        assert_eq!(usize::from((true, DiceSide::One).to_perfhash()), 6);
+        assert_eq!(usize::from((false, DiceSide::Six).to_perfhash()), 5);
+    }
+
+    #[test]
+    fn score_domain_validation() {
+        assert!(ScoreDomain::new(25, 1500).is_ok());
+        assert!(ScoreDomain::new(10, 8000).is_ok());
+        assert_eq!(ScoreDomain::new(0, 6000), Err(ScoreDomainError::ZeroGranularity));
+        assert_eq!(ScoreDomain::new(50, 6010), Err(ScoreDomainError::CapNotMultiple {granularity: 50, cap: 6010}));
+        assert_eq!(ScoreDomain::new(50, 0), Err(ScoreDomainError::CapNotMultiple {granularity: 50, cap: 0}));
+        // A single 5 scores 50
+        assert_eq!(ScoreDomain::new(100, 6000), Err(ScoreDomainError::UnrepresentableHand {granularity: 100, score: 50}));
+        assert_eq!(ScoreDomain::new(DEFAULT_GRANULARITY, DEFAULT_CAP), Ok(ScoreDomain::default()));
+    }
+
+    #[test]
+    fn score_domain_deserialise_validation() {
+        let domain = ScoreDomain::new(25, 1500).unwrap();
+        assert_eq!(serde_json::from_str::<ScoreDomain>(&serde_json::to_string(&domain).unwrap()).unwrap(), domain);
+        let error = serde_json::from_str::<ScoreDomain>(r#"{"granularity":0,"cap":6000}"#).unwrap_err();
+        assert_eq!(error.to_string(), "score granularity must be non-zero");
+        assert!(serde_json::from_str::<ScoreDomain>(r#"{"granularity":100,"cap":6000}"#).is_err());
+
+        // Maps validate their domain when loaded
+        let json = r#"{"map":[0.0],"domain":{"granularity":0,"cap":50}}"#;
+        assert!(serde_json::from_str::<ScoreMap<bool, f32>>(json).is_err());
+        let json = r#"{"map":[0.0,0.0],"domain":{"granularity":50,"cap":50}}"#;
+        assert!(serde_json::from_str::<ScoreMap<bool, f32>>(json).is_ok());
+    }
+
+    #[test]
+    fn map_deserialise_validation() {
+        let domain = ScoreDomain::new(50, 200).unwrap();
+        let mut map = ScoreMap::<[bool; 2], f32>::new(domain);
+        map[(FarkleScore::new(150), [true, false])] = 1.0;
+        let mut json = serde_json::to_value(&map).unwrap();
+        let loaded: ScoreMap<[bool; 2], f32> = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(loaded.domain(), domain);
+        assert!(loaded.iter().zip(map.iter()).all(|((a, x), (b, y))| a == b && x == y));
+        // Truncated data
+        json["map"].as_array_mut().unwrap().pop();
+        let error = serde_json::from_value::<ScoreMap<[bool; 2], f32>>(json).unwrap_err();
+        assert_eq!(error.to_string(), "expected a map of 16 keys but found 15");
+
+        let mut dense = PerfectHashMap::<(bool, DiceSide), f32>::new();
+        let mut sparse = PerfectHashMap::<(bool, DiceSide), f32, Sparse<f32>>::new();
+        dense[(true, DiceSide::Five)] = 1.0;
+        sparse[(true, DiceSide::Five)] = 1.0;
+        let mut dense_json = serde_json::to_value(&dense).unwrap();
+        let mut sparse_json = serde_json::to_value(&sparse).unwrap();
+        let loaded: PerfectHashMap<(bool, DiceSide), f32> = serde_json::from_value(dense_json.clone()).unwrap();
+        assert!(loaded.iter().zip(dense.iter()).all(|((a, x), (b, y))| a == b && x == y));
+        let loaded: PerfectHashMap<(bool, DiceSide), f32, Sparse<f32>> = serde_json::from_value(sparse_json.clone()).unwrap();
+        assert!(loaded.iter().zip(dense.iter()).all(|((a, x), (b, y))| a == b && x == y));
+        // Data for a different key type
+        assert!(serde_json::from_value::<PerfectHashMap<DiceSide, f32>>(dense_json.clone()).is_err());
+        assert!(serde_json::from_value::<PerfectHashMap<DiceSide, f32, Sparse<f32>>>(sparse_json.clone()).is_err());
+        // Truncated data
+        dense_json["map"].as_array_mut().unwrap().pop();
+        let error = serde_json::from_value::<PerfectHashMap<(bool, DiceSide), f32>>(dense_json).unwrap_err();
+        assert_eq!(error.to_string(), "expected a map of 12 keys but found 11");
+        sparse_json["map"]["size"] = 11.into();
+        let error = serde_json::from_value::<PerfectHashMap<(bool, DiceSide), f32, Sparse<f32>>>(sparse_json.clone()).unwrap_err();
+        assert_eq!(error.to_string(), "expected a map of 12 keys but found 11");
+        // Sparse keys must lie within the size
+        sparse_json["map"]["size"] = 12.into();
+        sparse_json["map"]["values"]["12"] = 2.0.into();
+        let error = serde_json::from_value::<PerfectHashMap<(bool, DiceSide), f32, Sparse<f32>>>(sparse_json).unwrap_err();
+        assert_eq!(error.to_string(), "map key 12 lies outside of the size 12");
+    }
+
+    #[test]
+    fn score_map() {
+        let domain = ScoreDomain::new(25, 1500).unwrap();
+        let mut map = ScoreMap::<[bool; 6], f32>::new(domain);
+        assert_eq!(map.set_size(), 60 * 64);
+        for (h, (key, _)) in map.iter().enumerate() {
+            assert_eq!(map.hash(&key), Some(h));
+        }
+        map[(FarkleScore::new(1475), [true; 6])] = 1.0;
+        assert_eq!(map.get(&(FarkleScore::new(1475), [true; 6])), Some(&1.0));
+        assert_eq!(map.get(&(FarkleScore::new(1500), [true; 6])), None);
+        assert_eq!(map.get(&(FarkleScore::new(30), [true; 6])), None);
+        // Same layout as the tuple over the default domain
+        let map = ScoreMap::<[bool; 6], f32>::new(ScoreDomain::default());
+        assert_eq!(map.set_size(), <(FarkleScore, [bool; 6])>::SET_SIZE);
+        for key in [(FarkleScore::new(0), [false; 6]), (FarkleScore::new(2350), [true, false, true, false, false, true])] {
+            assert_eq!(map.hash(&key), Some(usize::from(key.to_perfhash())));
+        }
+    }
+}
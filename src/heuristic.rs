@@ -0,0 +1,129 @@
+//! Rule of thumb strategies (e.g "bank at 300+ with 2 or less dice") for comparing against the
+//! optimal strategy
+
+use crate::farkle::{best_selection, count_sides, score, DiceSetSample};
+use crate::strategy::{Strategy, TurnState};
+use serde::{Deserialize, Serialize};
+
+/// How a heuristic picks which dice to keep out of a (non-bust) roll
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeepRule {
+    /// Always keep the highest scoring hand possible
+    Max,
+    /// Keep the fewest dice that still form a valid hand (preferring the higher score amongst those).
+    /// This leaves as many dice as possible to roll again with.
+    Minimum,
+}
+impl KeepRule {
+    /// Returns the dice to keep out of the given sample
+    pub fn select(&self, sample: &DiceSetSample) -> DiceSetSample {
+        match self {
+            Self::Max => return best_selection(sample.clone()),
+            Self::Minimum => {
+                let mut best = DiceSetSample::default();
+                let mut best_key = (usize::MAX, 0);
+                for selection in sample.iter_selections() {
+                    let select_score = score(count_sides(&selection.present())).score();
+                    if select_score == 0 {
+                        continue;
+                    }
+                    let n = selection.present().len();
+                    if n < best_key.0 || (n == best_key.0 && select_score > best_key.1) {
+                        best_key = (n, select_score);
+                        best = selection;
+                    }
+                }
+                return best;
+            }
+        }
+    }
+}
+
+/// A heuristic that keeps dice according to a KeepRule and then banks once the turn score reaches
+/// the threshold for the amount of dice that would be rolled next.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ThresholdStrat {
+    /// Minimum turn score (after keeping dice) to bank at. Index 0 is for when 1 die would be rolled next,
+    /// index 1 is for 2 dice etc...
+    ///  - Using up all the dice loops back round to rolling 6 dice, so index 5 is used for that case
+    pub thresholds: [u32; 6],
+    /// How dice are picked to be kept
+    pub keep: KeepRule,
+}
+impl ThresholdStrat {
+    pub fn new(thresholds: [u32; 6], keep: KeepRule) -> Self {
+        return Self {thresholds, keep};
+    }
+
+    /// Creates a heuristic that banks at the same score regardless of the dice left
+    pub fn uniform(threshold: u32, keep: KeepRule) -> Self {
+        return Self::new([threshold; 6], keep);
+    }
+
+    /// A family of commonly used rules of thumb, along with a short description of each
+    pub fn family() -> Vec<(String, Self)> {
+        let mut out = Vec::new();
+        for keep in [KeepRule::Max, KeepRule::Minimum] {
+            // Never roll again
+            out.push((format!("{:?} keep, always bank", keep), Self::uniform(0, keep)));
+            // Bank at a fixed score
+            for threshold in [300, 500, 1000, 2000] {
+                out.push((format!("{:?} keep, bank at {}+", keep, threshold), Self::uniform(threshold, keep)));
+            }
+            // Bank at lower scores when only a few dice are left
+            for threshold in [300, 500] {
+                let mut thresholds = [u32::MAX; 6];
+                thresholds[0] = threshold;
+                thresholds[1] = threshold;
+                out.push((format!("{:?} keep, bank at {}+ with <=2 dice", keep, threshold), Self::new(thresholds, keep)));
+            }
+            out.push((format!("{:?} keep, scaled by dice left", keep), Self::new([250, 300, 400, 1000, 2000, 3000], keep)));
+        }
+        return out;
+    }
+}
+impl Strategy for ThresholdStrat {
+    fn decide(&self, state: &TurnState, sample: &DiceSetSample) -> (DiceSetSample, bool) {
+        let selection = self.keep.select(sample);
+        let turn_score = state.score.score() + score(count_sides(&selection.present())).score();
+        let mut dice_left = sample.present().len() - selection.present().len();
+        if dice_left == 0 {
+            dice_left = 6;
+        }
+        return (selection, turn_score < self.thresholds[dice_left - 1]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::farkle::{DiceSide, FarkleScore};
+
+    #[test]
+    fn keep_rules() {
+        let sample = DiceSetSample::from_faces([1, 1, 5, 3, 4, 2]).unwrap();
+        // A low straight along with the other 1
+        assert_eq!(KeepRule::Max.select(&sample).faces(), [1, 1, 5, 3, 4, 2]);
+        assert_eq!(KeepRule::Minimum.select(&sample).present(), vec![DiceSide::One]);
+        let sample = DiceSetSample::from_faces([5, 0, 2, 2, 0, 6]).unwrap();
+        assert_eq!(KeepRule::Minimum.select(&sample).present(), vec![DiceSide::Five]);
+    }
+
+    #[test]
+    fn threshold_decisions() {
+        let heuristic = ThresholdStrat::new([300, 400, 500, 600, 700, 800], KeepRule::Max);
+        let state = TurnState::new(FarkleScore::new(200), [true; 6]);
+        // Keeps 100 leaving 5 dice, below the threshold of 700
+        let (selection, roll_again) = heuristic.decide(&state, &DiceSetSample::from_faces([1, 2, 3, 4, 6, 6]).unwrap());
+        assert_eq!((selection.faces(), roll_again), ([1, 0, 0, 0, 0, 0], true));
+        // Keeps 200 leaving 4 dice, above the threshold of 600
+        let state = TurnState::new(FarkleScore::new(450), [true; 6]);
+        assert!(!heuristic.decide(&state, &DiceSetSample::from_faces([1, 2, 3, 4, 6, 1]).unwrap()).1);
+        // Using up every die loops back round to the 6 dice threshold
+        let state = TurnState::new(FarkleScore::new(400), [true, true, false, false, false, false]);
+        let sample = DiceSetSample::from_faces([1, 5, 0, 0, 0, 0]).unwrap();
+        assert!(heuristic.decide(&state, &sample).1);
+        assert!(!ThresholdStrat::uniform(0, KeepRule::Max).decide(&state, &sample).1);
+        assert_eq!(ThresholdStrat::family().len(), 16);
+    }
+}
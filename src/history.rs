@@ -0,0 +1,180 @@
+//! Keeping the value tables of each Optimal_n computed along the way, to see how looking further
+//! ahead changes play.
+//!
+//! A state is a score and set of dice left to roll. Its decision is whether rolling those dice is
+//! expected to gain points (roll) or not (bank).
+
+use crate::farkle::FarkleScore;
+use crate::hash::ScoreMap;
+use crate::numeric::Numeric;
+use crate::optimal::OptimalStrat;
+use std::fmt;
+
+/// Number of decision changes listed by the Display of an IterationDiff
+const LISTED_CHANGES: usize = 20;
+
+/// Values of a single Optimal_n
+#[derive(Clone, Debug)]
+pub struct IterationSummary {
+    pub n: usize,
+    /// Expected score gain of every state
+    pub expected_scores: ScoreMap<[bool; 6], f64>,
+}
+impl IterationSummary {
+    pub fn new<T: Numeric>(optimal: &OptimalStrat<T>) -> Self {
+        let mut expected_scores = ScoreMap::new(optimal.domain());
+        for ((p, dice), slot) in expected_scores.iter_mut() {
+            *slot = optimal.query_score(p, dice).to_f64();
+        }
+        return Self {n: optimal.n, expected_scores};
+    }
+
+    /// Expected score of a round from 0 with all 6 dice
+    pub fn round_score(&self) -> f64 {
+        return self.expected_scores[(FarkleScore::new(0), [true; 6])];
+    }
+}
+
+/// Summaries of every iteration recorded while solving
+#[derive(Clone, Debug, Default)]
+pub struct SolveHistory {
+    pub iterations: Vec<IterationSummary>,
+}
+impl SolveHistory {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    pub fn record<T: Numeric>(&mut self, optimal: &OptimalStrat<T>) {
+        self.iterations.push(IterationSummary::new(optimal));
+    }
+
+    /// Differences between each pair of consecutive iterations recorded
+    pub fn diffs(&self) -> Vec<IterationDiff> {
+        return self.iterations.windows(2).map(|w| IterationDiff::new(&w[0], &w[1])).collect();
+    }
+}
+
+/// How a state's value changed between two iterations
+#[derive(Clone, Debug, PartialEq)]
+pub struct StateChange {
+    pub score: FarkleScore,
+    pub dice: [bool; 6],
+    pub before: f64,
+    pub after: f64,
+}
+impl StateChange {
+    pub fn change(&self) -> f64 {
+        return self.after - self.before;
+    }
+
+    /// Whether the state went from banking to rolling or the other way round
+    pub fn decision_changed(&self) -> bool {
+        return (self.before > 0.0) != (self.after > 0.0);
+    }
+}
+
+/// Changes from one iteration to the next
+#[derive(Clone, Debug, PartialEq)]
+pub struct IterationDiff {
+    pub from: usize,
+    pub to: usize,
+    pub round_score_before: f64,
+    pub round_score_after: f64,
+    /// Number of states whose value changed at all
+    pub changed: usize,
+    /// Number of states compared
+    pub states: usize,
+    pub mean_change: f64,
+    /// The state whose value changed the most. None if nothing changed.
+    pub largest_change: Option<StateChange>,
+    /// Every state that switched between banking and rolling, in order of score
+    pub decision_changes: Vec<StateChange>,
+}
+impl IterationDiff {
+    /// Compares two iterations of the same score domain
+    pub fn new(before: &IterationSummary, after: &IterationSummary) -> Self {
+        assert_eq!(before.expected_scores.domain(), after.expected_scores.domain(), "Iterations use different score domains");
+        let changes: Vec<StateChange> = before.expected_scores.iter()
+            .zip(after.expected_scores.iter())
+            // Having no dice left is the same state as having all 6
+            .filter(|(((_, dice), _), _)| dice.iter().any(|&b| b))
+            .map(|(((score, dice), &b), (_, &a))| StateChange {score, dice, before: b, after: a})
+            .collect();
+        let states = changes.len();
+        let mean_change = changes.iter().map(|c| c.change().abs()).sum::<f64>() / states.max(1) as f64;
+        let largest_change = changes.iter()
+            .filter(|c| c.change() != 0.0)
+            .max_by(|a, b| a.change().abs().total_cmp(&b.change().abs()))
+            .cloned();
+        let changed = changes.iter().filter(|c| c.change() != 0.0).count();
+        let decision_changes = changes.into_iter().filter(|c| c.decision_changed()).collect();
+        return Self {
+            from: before.n,
+            to: after.n,
+            round_score_before: before.round_score(),
+            round_score_after: after.round_score(),
+            changed,
+            states,
+            mean_change,
+            largest_change,
+            decision_changes,
+        };
+    }
+}
+impl fmt::Display for IterationDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dice_string = |dice: &[bool; 6]| dice.iter().map(|&b| if b { '1' } else { '0' }).collect::<String>();
+        let action = |value: f64| if value > 0.0 { "roll" } else { "bank" };
+        writeln!(
+            f, "Optimal_{} -> Optimal_{}: round score {:.4} -> {:.4} ({:+.4})",
+            self.from, self.to, self.round_score_before, self.round_score_after, self.round_score_after - self.round_score_before,
+        )?;
+        write!(f, "  {} of {} states changed value, mean change {:.4}", self.changed, self.states, self.mean_change)?;
+        match &self.largest_change {
+            Some(c) => writeln!(f, ", largest {:+.4} at score {} with dice {}", c.change(), c.score.score(), dice_string(&c.dice))?,
+            None => writeln!(f)?,
+        }
+        writeln!(f, "  {} states changed decision", self.decision_changes.len())?;
+        for c in self.decision_changes.iter().take(LISTED_CHANGES) {
+            writeln!(
+                f, "    score {} with dice {}: {} -> {} ({:.2} -> {:.2})",
+                c.score.score(), dice_string(&c.dice), action(c.before), action(c.after), c.before, c.after,
+            )?;
+        }
+        if self.decision_changes.len() > LISTED_CHANGES {
+            writeln!(f, "    and {} more", self.decision_changes.len() - LISTED_CHANGES)?;
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::farkle::{Dice, ScoreDomain};
+
+    #[test]
+    fn history_diffs() {
+        let mut history = SolveHistory::new();
+        let dices: [Dice; 6] = [Dice::default(); 6];
+        let optimal = OptimalStrat::solve(dices, ScoreDomain::new(50, 200).unwrap(), 2, Some(&mut history));
+        assert_eq!(optimal.n, 2);
+        assert_eq!(history.iterations.iter().map(|i| i.n).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(history.iterations[1].round_score(), optimal.query_score(FarkleScore::new(0), [true; 6]) as f64);
+
+        let diffs = history.diffs();
+        assert_eq!(diffs.len(), 1);
+        let diff = &diffs[0];
+        assert_eq!((diff.from, diff.to, diff.states), (1, 2, 4 * 63));
+        // Looking further ahead can only help
+        assert!(diff.round_score_after > diff.round_score_before);
+        assert!(diff.changed > 0 && diff.largest_change.is_some());
+        assert!(diff.decision_changes.iter().all(|c| c.decision_changed()));
+        assert!(diff.to_string().starts_with("Optimal_1 -> Optimal_2: round score "));
+
+        // Nothing changes when comparing an iteration with itself
+        let same = IterationDiff::new(&history.iterations[1], &history.iterations[1]);
+        assert_eq!((same.changed, same.mean_change, same.largest_change, same.decision_changes.len()), (0, 0.0, None, 0));
+    }
+}
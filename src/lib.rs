@@ -1,4 +1,31 @@
+// Lets the PerfectHashing derive refer to this crate by name from within the crate itself
+extern crate self as kcd_farkle_solver;
+
 pub mod farkle;
 pub mod optimal;
 pub mod hash;
-
+pub mod strategy;
+pub mod heuristic;
+pub mod game;
+pub mod agent;
+pub mod tournament;
+pub mod gamelog;
+pub mod notation;
+pub mod analysis;
+pub mod table;
+pub mod numeric;
+pub mod bias;
+pub mod sensitivity;
+pub mod ffi;
+pub mod policy;
+pub mod findings;
+pub mod plot;
+pub mod threshold;
+pub mod history;
+pub(crate) mod parallel;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "server")]
+pub mod server;
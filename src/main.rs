@@ -1,7 +1,21 @@
-use kcd_farkle_solver::farkle::{Dice, FarkleScore};
+use kcd_farkle_solver::analysis::MistakeReport;
+use kcd_farkle_solver::bias::{parse_rolls_csv, BiasEstimate};
+use kcd_farkle_solver::farkle::{Dice, DiceSetSample, FarkleScore, ScoreDomain, DEFAULT_GRANULARITY};
+use kcd_farkle_solver::findings::FindingsReport;
+use kcd_farkle_solver::gamelog::RoundLog;
+use kcd_farkle_solver::heuristic::ThresholdStrat;
+use kcd_farkle_solver::history::SolveHistory;
+use kcd_farkle_solver::notation::{parse_session, parse_sides};
 use kcd_farkle_solver::optimal::{OptimalStrat};
+use kcd_farkle_solver::plot::{decision_regions_svg, expected_gain_svg};
+use kcd_farkle_solver::policy::export_policy;
+use kcd_farkle_solver::strategy::{expected_round_score, TurnState, DEFAULT_TARGET};
+use kcd_farkle_solver::sensitivity::DiceSensitivity;
+use kcd_farkle_solver::threshold::ThresholdReport;
+use kcd_farkle_solver::tournament::Tournament;
 use std::fs::File;
 use std::io::{Write, BufReader};
+use std::path::Path;
 
 fn save(save_path: &str, obj: &OptimalStrat) {
     let json_data = serde_json::to_string(obj).expect("Failed to serialize data");
@@ -10,24 +24,303 @@ fn save(save_path: &str, obj: &OptimalStrat) {
         .expect("Failed to write JSON to file");
 }
 
-fn load(save_path: &str) -> OptimalStrat {
-    let file = File::open(SAVE_NAME).expect("Failed to open file");
+fn load(save_path: &str) -> Result<OptimalStrat, serde_json::Error> {
+    let file = File::open(save_path).expect("Failed to open file");
     let reader = BufReader::new(file);
-    let obj = serde_json::from_reader(reader).expect("Failed to deserialize object");
-    return obj;
+    return serde_json::from_reader(reader);
 }
 
-const SAVE_NAME: &str = "checkpoint.json";
-fn main() {
+/// Loads the checkpoint if there is one, otherwise calculates Optimal_2 and saves it
+fn load_or_solve() -> OptimalStrat {
+    return load_or_solve_domain(ScoreDomain::default());
+}
+
+/// Loads the checkpoint if there is one over the given score domain, otherwise calculates Optimal_2
+/// and saves it
+fn load_or_solve_domain(domain: ScoreDomain) -> OptimalStrat {
+    if Path::new(SAVE_NAME).exists() {
+        println!("Loading {}", SAVE_NAME);
+        match load(SAVE_NAME) {
+            Ok(optimal) if optimal.domain() == domain => return optimal,
+            Ok(_) => println!("Checkpoint uses a different score domain"),
+            // E.g checkpoints saved in an older format
+            Err(e) => println!("Checkpoint can't be used: {}", e),
+        }
+    }
     let dices = [Dice::default(); 6];
-    println!("Calculating Optimal_0");
-    let mut optimal = OptimalStrat::new(dices);
     println!("Calculating Optimal_1");
-    optimal = optimal.iterate();
+    let optimal_1 = OptimalStrat::with_domain(dices, domain);
+    println!("Calculating Optimal_2");
+    let optimal = optimal_1.iterate();
     println!("Saving");
     save(SAVE_NAME, &optimal);
+    return optimal;
+}
+
+/// Compares the expected round score of the heuristics against the optimal strategy
+fn heuristics() {
+    let optimal = load_or_solve();
+    println!("Optimal_{}: {}", optimal.n, optimal.query_score(FarkleScore::new(0), [true; 6]));
+    for (name, heuristic) in ThresholdStrat::family() {
+        println!("{}: {}", name, expected_round_score(&heuristic, &optimal.dices, optimal.domain()));
+    }
+}
+
+/// Plays the optimal strategy and heuristics against each other, writing results to <prefix>.json/.csv
+fn tournament(prefix: &str, matches_per_pairing: usize, seed: u64) {
+    let optimal = load_or_solve();
+    let dices = optimal.dices;
+    let mut tournament = Tournament::new(matches_per_pairing, DEFAULT_TARGET, seed);
+    tournament.register(format!("Optimal_{}", optimal.n), optimal, dices);
+    for (name, heuristic) in ThresholdStrat::family() {
+        tournament.register(name, heuristic, dices);
+    }
+    let report = tournament.run();
+    for standing in report.standings.iter() {
+        println!("{}: {:.1}% won, elo {:.0}", standing.name, standing.win_rate * 100.0, standing.elo);
+    }
+    std::fs::write(format!("{}.json", prefix), report.to_json()).expect("Failed to write report");
+    std::fs::write(format!("{}_standings.csv", prefix), report.standings_csv()).expect("Failed to write report");
+    std::fs::write(format!("{}_pairings.csv", prefix), report.pairings_csv()).expect("Failed to write report");
+}
+
+/// Records a round played by the given heuristic (or the optimal strategy if none given) to a log file
+fn record(log_path: &str, seed: u64, heuristic: Option<usize>) {
+    let mut family = ThresholdStrat::family();
+    let heuristic = match heuristic {
+        Some(i) if i >= family.len() => {
+            println!("Invalid heuristic index {}: must be 0 to {}", i, family.len() - 1);
+            return;
+        }
+        Some(i) => Some(family.swap_remove(i)),
+        None => None,
+    };
+    let optimal = load_or_solve();
+    let start = TurnState::new(FarkleScore::new(0), [true; 6]);
+    let log = match heuristic {
+        Some((name, heuristic)) => {
+            println!("Playing as: {}", name);
+            RoundLog::record(&heuristic, &optimal.dices, start, seed)
+        }
+        None => RoundLog::record(&optimal, &optimal.dices, start, seed),
+    };
+    let json_data = serde_json::to_string_pretty(&log).expect("Failed to serialize log");
+    std::fs::write(log_path, json_data).expect("Failed to write log");
+}
+
+/// Replays a logged round, annotating each decision with its expected score loss versus optimal
+fn replay(log_path: &str) {
+    let file = File::open(log_path).expect("Failed to open log");
+    let log: RoundLog = serde_json::from_reader(BufReader::new(file)).expect("Failed to deserialize log");
+    let optimal = load_or_solve();
+    for annotated in log.annotate(&optimal) {
+        let record = &annotated.record;
+        print!("Score {:>4} | rolled {} | ", record.state.score.score(), record.roll);
+        match &annotated.optimal_decision {
+            None => println!("bust"),
+            Some((selection, roll_again)) => println!(
+                "kept {} and {} (EV {:.1}) | optimal: keep {} and {} (EV {:.1}) | loss {:.1}",
+                record.selection, roll_or_bank(record.roll_again), annotated.chosen_value,
+                selection, roll_or_bank(*roll_again), annotated.optimal_value,
+                annotated.loss,
+            ),
+        }
+    }
+}
+
+/// Ranks the mistakes made in a session of rounds written in dice notation
+fn analyse(session_path: &str) {
+    let text = std::fs::read_to_string(session_path).expect("Failed to read session");
+    let optimal = load_or_solve();
+    let rounds = match parse_session(&text, &optimal.dices) {
+        Ok(rounds) => rounds,
+        Err(e) => {
+            println!("Failed to parse {}: {}", session_path, e);
+            return;
+        }
+    };
+    print!("{}", MistakeReport::new(&rounds, &optimal));
+}
+
+/// Writes the tables of the solved strategy to <prefix>_*.csv and <prefix>.md
+fn findings(prefix: &str, step: usize) {
+    let report = FindingsReport::new(&load_or_solve());
+    std::fs::write(format!("{}_expected_scores.csv", prefix), report.expected_scores_csv()).expect("Failed to write findings");
+    std::fs::write(format!("{}_bust_prob.csv", prefix), report.bust_prob_csv()).expect("Failed to write findings");
+    std::fs::write(format!("{}_thresholds.csv", prefix), report.thresholds_csv()).expect("Failed to write findings");
+    std::fs::write(format!("{}.md", prefix), report.markdown(step)).expect("Failed to write findings");
+}
+
+/// Prints what every way of playing the roll is expected to gain
+fn explain(score: u32, roll: &str) {
+    let sides = parse_sides(roll).filter(|sides| !sides.is_empty() && sides.len() <= 6).expect("Invalid roll");
+    let mut sample = DiceSetSample::default();
+    for (slot, side) in sample.sample.iter_mut().zip(sides) {
+        *slot = Some(side);
+    }
+    let optimal = load_or_solve();
+    if optimal.domain().index(FarkleScore::new(score)).is_none() {
+        println!("Score {} lies outside of the solved score domain", score);
+        return;
+    }
+    print!("{}", optimal.explain_decision(FarkleScore::new(score), &sample));
+}
+
+/// Computes Optimal_1 to Optimal_n in both f32 and f64, printing how far apart the two get
+fn drift(iterations: usize) {
+    let dices: [Dice; 6] = [Dice::default(); 6];
+    let mut single = OptimalStrat::new(dices);
+    let mut double = OptimalStrat::new([Dice::<f64>::default(); 6]);
+    for n in 1..=iterations {
+        if n > 1 {
+            single = single.iterate();
+            double = double.iterate();
+        }
+        println!(
+            "Optimal_{}: f32 {} | f64 {} | max difference {:e}",
+            n, single.query_score(FarkleScore::new(0), [true; 6]), double.query_score(FarkleScore::new(0), [true; 6]),
+            single.max_difference(&double),
+        );
+    }
+}
+
+/// Computes Optimal_1 to Optimal_n, printing how the values and decisions change with each iteration
+fn history(iterations: usize) {
+    let mut history = SolveHistory::new();
+    let dices: [Dice; 6] = [Dice::default(); 6];
+    OptimalStrat::solve(dices, ScoreDomain::default(), iterations, Some(&mut history));
+    for diff in history.diffs() {
+        print!("{}", diff);
+    }
+}
+
+/// Estimates the weights of each die in a CSV of observed rolls
+fn estimate_dice(csv_path: &str, concentration: f64) {
+    let text = std::fs::read_to_string(csv_path).expect("Failed to read rolls");
+    let dice = match parse_rolls_csv(&text) {
+        Ok(dice) => dice,
+        Err(e) => {
+            println!("Failed to parse {}: {}", csv_path, e);
+            return;
+        }
+    };
+    for (name, counts) in dice {
+        match BiasEstimate::new(counts, concentration, 0.95) {
+            Ok(estimate) => println!("{}: {}", name, estimate),
+            Err(e) => println!("Can't estimate {}: {}", name, e),
+        }
+    }
+}
+
+/// Prints how much each face weight of each die matters to the optimal strategy's expected round score
+fn sensitivity(step: f32) {
+    let optimal = load_or_solve();
+    let sensitivity = DiceSensitivity::new(&optimal, step);
+    print!("{}", sensitivity);
+    println!("Most valuable probability to gain:");
+    for (die, side, derivative) in sensitivity.ranked().into_iter().take(6) {
+        println!("  die {} side {}: {:.2}", die + 1, side.face(), derivative);
+    }
+}
+
+fn roll_or_bank(roll_again: bool) -> &'static str {
+    if roll_again {
+        return "roll";
+    }
+    return "bank";
+}
 
-    // let mut optimal = load(SAVE_NAME);
-    // optimal.iterate();
-    // println!("{:?}", optimal.expected_scores[(FarkleScore::new(0), [true; 6])]);
-}
\ No newline at end of file
+const SAVE_NAME: &str = "checkpoint.json";
+const USAGE: &str = "Usage:
+    kcd_farkle_solver solve [score cap] [score granularity]
+    kcd_farkle_solver heuristics
+    kcd_farkle_solver tournament <output prefix> [matches per pairing] [seed]
+    kcd_farkle_solver record <log file> [seed] [heuristic index]
+    kcd_farkle_solver replay <log file>
+    kcd_farkle_solver analyse <session file>
+    kcd_farkle_solver export-tables <directory>
+    kcd_farkle_solver export-policy <file>
+    kcd_farkle_solver findings <output prefix> [markdown score step]
+    kcd_farkle_solver plot <output prefix>
+    kcd_farkle_solver thresholds
+    kcd_farkle_solver explain <turn score> <roll, e.g 155236>
+    kcd_farkle_solver drift [iterations]
+    kcd_farkle_solver history [iterations]
+    kcd_farkle_solver estimate-dice <rolls csv> [prior concentration]
+    kcd_farkle_solver sensitivity [step]";
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.iter().map(|s| s.as_str()).collect::<Vec<_>>().as_slice() {
+        [] | ["solve"] => {
+            let optimal = load_or_solve();
+            println!("Optimal_{}: {}", optimal.n, optimal.query_score(FarkleScore::new(0), [true; 6]));
+        }
+        ["solve", cap, rest @ ..] if rest.len() <= 1 => {
+            let cap = cap.parse().expect("Invalid score cap");
+            let granularity = rest.first().map(|s| s.parse().expect("Invalid score granularity")).unwrap_or(DEFAULT_GRANULARITY);
+            let domain = match ScoreDomain::new(granularity, cap) {
+                Ok(domain) => domain,
+                Err(e) => {
+                    println!("Invalid score domain: {}", e);
+                    return;
+                }
+            };
+            let optimal = load_or_solve_domain(domain);
+            println!("Optimal_{}: {}", optimal.n, optimal.query_score(FarkleScore::new(0), [true; 6]));
+        }
+        ["heuristics"] => heuristics(),
+        ["tournament", prefix, rest @ ..] if rest.len() <= 2 => {
+            let matches = rest.first().map(|s| s.parse().expect("Invalid match count")).unwrap_or(100);
+            let seed = rest.get(1).map(|s| s.parse().expect("Invalid seed")).unwrap_or(0);
+            tournament(prefix, matches, seed);
+        }
+        ["record", log_path, rest @ ..] if rest.len() <= 2 => {
+            let seed = rest.first().map(|s| s.parse().expect("Invalid seed")).unwrap_or(0);
+            let heuristic = rest.get(1).map(|s| s.parse().expect("Invalid heuristic index"));
+            record(log_path, seed, heuristic);
+        }
+        ["replay", log_path] => replay(log_path),
+        ["analyse", session_path] => analyse(session_path),
+        ["export-tables", dir] => {
+            load_or_solve().save_tables(dir).expect("Failed to save tables");
+        }
+        ["export-policy", path] => {
+            let policy = export_policy(&load_or_solve()).expect("Failed to export policy");
+            std::fs::write(path, policy).expect("Failed to save policy");
+        }
+        ["findings", prefix, rest @ ..] if rest.len() <= 1 => {
+            findings(prefix, rest.first().map(|s| s.parse().expect("Invalid score step")).unwrap_or(10));
+        }
+        ["plot", prefix] => {
+            let report = FindingsReport::new(&load_or_solve());
+            std::fs::write(format!("{}_expected_gain.svg", prefix), expected_gain_svg(&report)).expect("Failed to write plot");
+            std::fs::write(format!("{}_regions.svg", prefix), decision_regions_svg(&report)).expect("Failed to write plot");
+        }
+        ["thresholds"] => {
+            print!("{}", ThresholdReport::new(&load_or_solve()));
+        }
+        ["explain", score, roll] => {
+            explain(score.parse().expect("Invalid score"), roll);
+        }
+        ["drift", rest @ ..] if rest.len() <= 1 => {
+            drift(rest.first().map(|s| s.parse().expect("Invalid iteration count")).unwrap_or(2));
+        }
+        ["history", rest @ ..] if rest.len() <= 1 => {
+            history(rest.first().map(|s| s.parse().expect("Invalid iteration count")).unwrap_or(3));
+        }
+        ["estimate-dice", csv_path, rest @ ..] if rest.len() <= 1 => {
+            let concentration = match rest.first().map(|s| s.parse()).unwrap_or(Ok(1.0)) {
+                Ok(concentration) => concentration,
+                Err(e) => {
+                    println!("Invalid prior concentration \"{}\": {}", rest[0], e);
+                    return;
+                }
+            };
+            estimate_dice(csv_path, concentration);
+        }
+        ["sensitivity", rest @ ..] if rest.len() <= 1 => {
+            sensitivity(rest.first().map(|s| s.parse().expect("Invalid step")).unwrap_or(0.01));
+        }
+        _ => println!("{}", USAGE),
+    }
+}
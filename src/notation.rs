@@ -0,0 +1,159 @@
+//! Parsing rounds typed in using dice notation (e.g rounds played in the game itself)
+//!
+//! Each non-empty line is a single roll:
+//! ```text
+//! <rolled sides> <kept sides> <r|b>
+//! ```
+//! E.g `115346 115 r` means 1,1,5,3,4,6 was rolled, 1,1,5 was kept and the player rolled again.
+//! A roll that went bust is written on its own (e.g `2346`). Everything after a `#` is ignored.
+//!
+//! A round ends whenever a player banks or goes bust, with the next line starting a new round.
+//! Rolled sides are assigned to the dice that are left in order, so when using a loadout of
+//! different dice, sides should be written in the order of the dice.
+
+use crate::farkle::{count_sides, not_busted, score, Dice, DiceSet, DiceSetSample, DiceSide, FarkleScore};
+use crate::game::{RollRecord, RoundEnd, RoundResult};
+use crate::gamelog::RoundLog;
+use crate::strategy::TurnState;
+use std::error::Error;
+use std::fmt;
+
+/// Failure to parse dice notation
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NotationError {
+    /// Line (starting from 1) the error occured on
+    pub line: usize,
+    pub message: String,
+}
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "line {}: {}", self.line, self.message);
+    }
+}
+impl Error for NotationError {}
+
+/// Parses a string of sides such as "1153"
+pub fn parse_sides(s: &str) -> Option<Vec<DiceSide>> {
+    return s.chars().map(DiceSide::from_face).collect();
+}
+
+/// Parses a session of rounds written in dice notation. See the module documentation for the format.
+pub fn parse_session(text: &str, dices: &[Dice; 6]) -> Result<Vec<RoundLog>, NotationError> {
+    let mut rounds = Vec::new();
+    let mut current: Option<RoundLog> = None;
+    let mut state = TurnState::new(FarkleScore::new(0), [true; 6]);
+    for (i, line) in text.lines().enumerate() {
+        let err = |message: String| NotationError {line: i + 1, message};
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let log = current.get_or_insert_with(|| {
+            state = TurnState::new(FarkleScore::new(0), [true; 6]);
+            return RoundLog {seed: None, dices: *dices, start: state, rolls: Vec::new(), result: None};
+        });
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        // Assign the rolled sides to the dice left
+        let rolled = parse_sides(tokens[0]).ok_or_else(|| err(format!("\"{}\" is not a list of sides", tokens[0])))?;
+        let dice_left = state.dice.iter().filter(|&&b| b).count();
+        if rolled.len() != dice_left {
+            return Err(err(format!("{} dice rolled but {} dice were left", rolled.len(), dice_left)));
+        }
+        let mut roll = DiceSetSample::default();
+        for (slot, side) in roll.sample.iter_mut().zip(state.dice).filter(|(_, b)| *b).map(|(s, _)| s).zip(rolled) {
+            *slot = Some(side);
+        }
+        let busted = !not_busted(&count_sides(&roll.present()));
+        match tokens[1..] {
+            [] => {
+                if !busted {
+                    return Err(err("roll can score so a decision is needed".to_string()));
+                }
+                log.rolls.push(RollRecord {state, roll, selection: DiceSetSample::default(), roll_again: false});
+                log.result = Some(RoundResult {score: 0, rolls: log.rolls.len(), end: RoundEnd::Bust});
+                rounds.push(current.take().unwrap());
+            }
+            [kept, decision] => {
+                let kept = parse_sides(kept).ok_or_else(|| err(format!("\"{}\" is not a list of sides", kept)))?;
+                let selection = roll.select_sides(&kept).ok_or_else(|| err("kept dice were not rolled".to_string()))?;
+                let select_score = score(count_sides(&selection.present())).score();
+                if select_score == 0 {
+                    return Err(err("kept dice do not form a valid hand".to_string()));
+                }
+                let roll_again = match decision {
+                    "r" => true,
+                    "b" => false,
+                    _ => return Err(err(format!("expected r or b but found \"{}\"", decision))),
+                };
+                log.rolls.push(RollRecord {state, roll: roll.clone(), selection: selection.clone(), roll_again});
+                state.score = FarkleScore::new(state.score.score() + select_score);
+                if roll_again {
+                    state.dice = DiceSet::new(dices, state.dice).new_subset(&selection.present_mask().map(|b| !b)).select_mask;
+                    // Having no dice means the player loops back round to 6-dice
+                    if state.dice == [false; 6] {
+                        state.dice = [true; 6];
+                    }
+                } else {
+                    log.result = Some(RoundResult {score: state.score.score(), rolls: log.rolls.len(), end: RoundEnd::Banked});
+                    rounds.push(current.take().unwrap());
+                }
+            }
+            _ => return Err(err("expected \"<rolled> <kept> <r|b>\" or \"<rolled>\"".to_string())),
+        }
+    }
+    // Keep any round that was left unfinished
+    if let Some(log) = current {
+        rounds.push(log);
+    }
+    return Ok(rounds);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Vec<RoundLog>, NotationError> {
+        return parse_session(text, &[Dice::default(); 6]);
+    }
+
+    #[test]
+    fn valid_sessions() {
+        let rounds = parse("
+            # A round ending in a bust
+            115346 115 r
+            226
+            155236 1 b   # Banked early
+
+            123456 123456 r
+            223346
+            155236 155 r
+        ").unwrap();
+        assert_eq!(rounds.len(), 4);
+        assert_eq!(rounds[0].rolls.len(), 2);
+        assert_eq!(rounds[0].rolls[1].state, TurnState {score: FarkleScore::new(250), dice: [false, false, false, true, true, true], ..rounds[0].start});
+        assert_eq!(rounds[0].result.map(|r| (r.score, r.end)), Some((0, RoundEnd::Bust)));
+        assert_eq!(rounds[1].result.map(|r| (r.score, r.rolls, r.end)), Some((100, 1, RoundEnd::Banked)));
+        assert_eq!(rounds[1].rolls[0].selection, DiceSetSample::from_faces([1, 0, 0, 0, 0, 0]).unwrap());
+        // Using every die loops back round to 6 dice
+        assert_eq!(rounds[2].rolls[1].state.dice, [true; 6]);
+        assert_eq!(rounds[2].rolls[1].state.score, FarkleScore::new(1500));
+        // Unfinished rounds are kept
+        assert!(rounds[3].result.is_none());
+        assert!(parse("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn invalid_sessions() {
+        let error = |text: &str| parse(text).unwrap_err();
+        assert_eq!(error("1153x6 115 r"), NotationError {line: 1, message: "\"1153x6\" is not a list of sides".to_string()});
+        assert_eq!(error("11534 115 r").message, "5 dice rolled but 6 dice were left");
+        assert_eq!(error("115346 115 r\n123456 1 r").message, "6 dice rolled but 3 dice were left");
+        assert_eq!(error("115346").message, "roll can score so a decision is needed");
+        assert_eq!(error("115346 1 x").message, "expected r or b but found \"x\"");
+        assert_eq!(error("115346 5555 r").message, "kept dice were not rolled");
+        assert_eq!(error("115346 13 r").message, "kept dice do not form a valid hand");
+        assert_eq!(error("115346 1 r r").message, "expected \"<rolled> <kept> <r|b>\" or \"<rolled>\"");
+        assert_eq!(error("\n# comment\n115346 15").line, 3);
+        assert_eq!(error("115346 1 r\n\n12345").to_string(), "line 3: roll can score so a decision is needed");
+    }
+}
@@ -0,0 +1,91 @@
+//! Number types the solver can compute probabilities and expected scores with.
+//!
+//! f32 is the default. f64 and exact rationals (BigRational) are useful for checking published
+//! numbers exactly and measuring how much floating point error builds up across iterations.
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, ToPrimitive, Zero};
+use std::fmt::Debug;
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+/// A number type probabilities and expected scores can be computed in
+pub trait Numeric:
+    Clone + Default + PartialOrd + Debug + Send + Sync
+    + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Div<Output = Self> + Neg<Output = Self>
+    + AddAssign + SubAssign
+{
+    fn zero() -> Self;
+    fn one() -> Self;
+    fn from_u32(n: u32) -> Self;
+    /// The fraction numer/denom
+    fn ratio(numer: u32, denom: u32) -> Self;
+    /// Closest value to the given float
+    fn from_f64(x: f64) -> Self;
+    fn to_f64(&self) -> f64;
+}
+
+impl Numeric for f32 {
+    fn zero() -> Self {
+        return 0.0;
+    }
+    fn one() -> Self {
+        return 1.0;
+    }
+    fn from_u32(n: u32) -> Self {
+        return n as f32;
+    }
+    fn ratio(numer: u32, denom: u32) -> Self {
+        return numer as f32 / denom as f32;
+    }
+    fn from_f64(x: f64) -> Self {
+        return x as f32;
+    }
+    fn to_f64(&self) -> f64 {
+        return *self as f64;
+    }
+}
+
+impl Numeric for f64 {
+    fn zero() -> Self {
+        return 0.0;
+    }
+    fn one() -> Self {
+        return 1.0;
+    }
+    fn from_u32(n: u32) -> Self {
+        return n as f64;
+    }
+    fn ratio(numer: u32, denom: u32) -> Self {
+        return numer as f64 / denom as f64;
+    }
+    fn from_f64(x: f64) -> Self {
+        return x;
+    }
+    fn to_f64(&self) -> f64 {
+        return *self;
+    }
+}
+
+/// Exact arithmetic. Much slower than floats, so best kept to checking a handful of values.
+impl Numeric for BigRational {
+    fn zero() -> Self {
+        return Zero::zero();
+    }
+    fn one() -> Self {
+        return One::one();
+    }
+    fn from_u32(n: u32) -> Self {
+        return BigRational::from_integer(BigInt::from(n));
+    }
+    fn ratio(numer: u32, denom: u32) -> Self {
+        return BigRational::new(BigInt::from(numer), BigInt::from(denom));
+    }
+    /// Floats are exactly representable as rationals. Non-finite values become 0.
+    fn from_f64(x: f64) -> Self {
+        return BigRational::from_float(x).unwrap_or_default();
+    }
+    fn to_f64(&self) -> f64 {
+        return ToPrimitive::to_f64(self).unwrap_or(f64::NAN);
+    }
+}
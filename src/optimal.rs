@@ -1,64 +1,126 @@
 //! Computation of maximising expected score for a Farkle game using the Optimal_n iteration algorithm
 //! described in the README.md
 
-use crate::hash::{PerfectHash, PerfectHashMap, PerfectHashing};
-use crate::farkle::{best_score, best_selection, count_sides, not_busted, score, Dice, DiceSet, DiceSetSample, FarkleScore};
-use rayon::prelude::*;
-use indicatif::ParallelProgressIterator;
+use crate::hash::{PerfectHashMap, ScoreMap};
+use crate::history::SolveHistory;
+use crate::farkle::{best_score, best_selection, count_sides, not_busted, score, Dice, DiceSet, DiceSetSample, FarkleScore, ScoreDomain};
+use crate::parallel::for_each_progress;
+use crate::numeric::Numeric;
+use crate::table::{FlatPerfectHashMap, FlatScoreMap, TableBytes};
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io;
+use std::ops::Deref;
+use std::path::Path;
+
+/// Version of the layout of saved strategies (checkpoints). Increased whenever a change would make older
+/// checkpoints load with the wrong values, so they get rejected instead.
+///  - 1: Hold tables are keyed by the derived DiceSetSample hashing. Checkpoints from before (which have
+///    no format) stored each hold value against the wrong sample.
+pub const CHECKPOINT_FORMAT: u32 = 1;
+
+/// Marks the checkpoint format a strategy was saved with. Deserialising fails for any other format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "u32", into = "u32")]
+struct CheckpointFormat;
+impl TryFrom<u32> for CheckpointFormat {
+    type Error = String;
+
+    fn try_from(format: u32) -> Result<Self, Self::Error> {
+        if format != CHECKPOINT_FORMAT {
+            return Err(format!("checkpoint format {} is not supported (expected {}), solve it again", format, CHECKPOINT_FORMAT));
+        }
+        return Ok(Self);
+    }
+}
+impl From<CheckpointFormat> for u32 {
+    fn from(_: CheckpointFormat) -> Self {
+        return CHECKPOINT_FORMAT;
+    }
+}
 
 /// Manages calculation and storage of results for each calculation of Optimal_n (described in the README.md).
 /// new() computes payoffs for Optimal_1 and iterate() computes payoffs for Optimal_n+1
 #[derive(Debug, Serialize, Deserialize)]
-pub struct OptimalStrat {
+pub struct OptimalStrat<T: Numeric = f32> {
     /// Expected score gain values for all possible scores and die subsets
-    pub expected_scores: PerfectHashMap<(FarkleScore, [bool; 6]), f32>,
+    pub expected_scores: ScoreMap<[bool; 6], T>,
     /// Expected score gain values for all possible scores and die subsets 
     /// assuming the player is definitely going to "Hold" (roll again).
     /// Tuple output of the hashmap stores the expected score gain and the dice that should
     /// be selected to form the hand.
-    expected_hold: PerfectHashMap<(FarkleScore, DiceSetSample), (f32, DiceSetSample)>,
+    expected_hold: ScoreMap<DiceSetSample, (T, DiceSetSample)>,
     /// The die weightings this strategy is based on
-    pub dices: [Dice; 6],
+    pub dices: [Dice<T>; 6],
     /// Busting probabilities of the dices
-    pub bust_prob: PerfectHashMap<[bool; 6], f32>,
+    pub bust_prob: PerfectHashMap<[bool; 6], T>,
     /// Number of rolls until the "Terminate" strategy must be used
     pub n: usize,
+    /// Checkpoints without a format are from before CHECKPOINT_FORMAT was introduced and fail to load
+    format: CheckpointFormat,
 }
-impl OptimalStrat {
-    /// Computes the expected score for the Optimal_1 strategy with the given die
-    pub fn new(dices: [Dice; 6]) -> Self {
+impl<T: Numeric> OptimalStrat<T> {
+    /// Computes the expected score for the Optimal_1 strategy with the given die over the default score domain
+    pub fn new(dices: [Dice<T>; 6]) -> Self {
+        return Self::with_domain(dices, ScoreDomain::default());
+    }
+
+    /// Computes the expected score for the Optimal_1 strategy with the given die, keeping track of
+    /// every score in the given domain.
+    ///
+    /// The cap of the domain is treated as the score needed to win. Reaching it ends the round.
+    pub fn with_domain(dices: [Dice<T>; 6], domain: ScoreDomain) -> Self {
         let bust_prob = Self::generate_busting_probabilities(&dices);
         // Since we computing Optimal_1, the "hold" decision is not applicable so we can skip computing it...
 
-        let mut expected_scores = PerfectHashMap::<(FarkleScore, [bool; 6]), f32>::new();
+        let mut expected_scores = ScoreMap::<[bool; 6], T>::new(domain);
+        let set_size = expected_scores.set_size() as u64;
         // For all possible score and dice subset product combinations
-        expected_scores.iter_mut()
-        .par_bridge()
-        .progress_count(<(FarkleScore, [bool; 6])>::SET_SIZE as u64)
-        .for_each(|((p, selection), dataslot)| {
+        for_each_progress(expected_scores.iter_mut(), set_size, |((p, selection), dataslot)| {
             // Calculate expected loss from busting
-            let expected_bust_loss = p.score() as f32 * bust_prob[selection];
+            let expected_bust_loss = T::from_u32(p.score()) * bust_prob[selection].clone();
             // Calculate expected gain when not busting
-            let mut expected_score_gain = 0.0;
+            let mut expected_score_gain = T::zero();
             let diceset = DiceSet::new(&dices, selection);
             for (sample_wrapped, prob) in diceset.iter_outcomes() {
-                expected_score_gain += prob * best_score(count_sides(&sample_wrapped.present())).score() as f32;
+                expected_score_gain += prob * T::from_u32(best_score(count_sides(&sample_wrapped.present())).score());
             }
             // Store net expected gain
             *dataslot = expected_score_gain - expected_bust_loss;
         });
         // Having no dice means the player loops back round to 6-dice. Thus for any farkle score P,
         // it should be that Optimal(P, no_dice)=Optimal(P, dice)
-        for p in (0..FarkleScore::SET_SIZE).map(|h| FarkleScore::from_perfhash(PerfectHash::new(h))) {
-            expected_scores[(p, [false; 6])] = expected_scores[(p, [true; 6])];
+        for p in domain.scores() {
+            expected_scores[(p, [false; 6])] = expected_scores[(p, [true; 6])].clone();
         }
-        return Self {expected_scores, expected_hold: PerfectHashMap::new(), dices, bust_prob, n: 1};
+        return Self {expected_scores, expected_hold: ScoreMap::new(domain), dices, bust_prob, n: 1, format: CheckpointFormat};
     }
     
+    /// Computes the expected score for the Optimal_n strategy, where n is the number of iterations (at least 1).
+    ///
+    /// A summary of every Optimal_1 to Optimal_n along the way is recorded into history if given.
+    pub fn solve(dices: [Dice<T>; 6], domain: ScoreDomain, iterations: usize, mut history: Option<&mut SolveHistory>) -> Self {
+        assert!(iterations >= 1, "At least 1 iteration is needed");
+        let mut optimal = Self::with_domain(dices, domain);
+        for n in 1..=iterations {
+            if n > 1 {
+                optimal = optimal.iterate();
+            }
+            if let Some(history) = history.as_deref_mut() {
+                history.record(&optimal);
+            }
+        }
+        return optimal;
+    }
+
+    /// The range of scores this strategy keeps track of
+    pub fn domain(&self) -> ScoreDomain {
+        return self.expected_scores.domain();
+    }
+
     /// Returns the expected score of this strategy with the given current score and boolean mask of dice left
-    pub fn query_score(&self, score: FarkleScore, die: [bool; 6]) -> f32 {
-        return self.expected_scores[(score, die)];
+    pub fn query_score(&self, score: FarkleScore, die: [bool; 6]) -> T {
+        return self.expected_scores[(score, die)].clone();
     }
 
     /// Returns the decision used by this strategy with the given current score and dice sample.
@@ -69,59 +131,130 @@ impl OptimalStrat {
     ///  - 0 = End turn here
     ///  - 1 = Roll again
     pub fn query_decision(&self, score: FarkleScore, sample: DiceSetSample) -> (DiceSetSample, bool) {
-        // Calculate expected loss from busting
-        let expected_bust_loss = score.score() as f32 * self.bust_prob[sample.present_mask()];
         // Calculate payoffs
-        let terminate = best_score(count_sides(&sample.present())).score() as f32;
+        let terminate = T::from_u32(best_score(count_sides(&sample.present())).score());
         let (hold, hold_selection) = self.expected_hold[(score, sample.clone())].clone();
-        if terminate > hold {
+        // Ties (e.g reaching the cap either way) are banked
+        if terminate >= hold {
             return (best_selection(sample), false);
         }
         return (hold_selection, true);
     }
 
+    /// Returns the expected score gain of making the given decision with the given current score and dice sample.
+    ///
+    /// Selections that do not form a valid hand are valued as a bust.
+    pub fn query_decision_value(&self, current_score: FarkleScore, sample: &DiceSetSample, selection: &DiceSetSample, roll_again: bool) -> T {
+        let select_score = score(count_sides(&selection.present())).score();
+        if select_score == 0 {
+            return -T::from_u32(current_score.score());
+        }
+        if !roll_again {
+            return T::from_u32(select_score);
+        }
+        let remaining = DiceSet::new(&self.dices, sample.present_mask()).new_subset(&selection.present_mask().map(|b| !b));
+        return self.hold_value(current_score, select_score, remaining.select_mask);
+    }
+
+    /// Returns the expected score gain of holding a selection worth select_score and rolling the remaining dice.
+    ///
+    /// Reaching the cap of the score domain wins the game, so the round ends there with nothing more to gain
+    /// (or lose) from rolling again.
+    fn hold_value(&self, current_score: FarkleScore, select_score: u32, remaining: [bool; 6]) -> T {
+        let next_score = current_score.score() + select_score;
+        if next_score >= self.domain().cap() {
+            return T::from_u32(select_score);
+        }
+        return T::from_u32(select_score) + self.expected_scores[(FarkleScore::new(next_score), remaining)].clone();
+    }
+
+    /// Returns the best decision possible with the given current score and dice sample, along with its expected
+    /// score gain. Unlike query_decision(), rolling again is valued using this strategy's own expected scores.
+    ///
+    /// Rolls that have gone bust have no decision to make (an empty selection is returned) and are valued at
+    /// losing the current score.
+    pub fn query_best_decision(&self, current_score: FarkleScore, sample: &DiceSetSample) -> ((DiceSetSample, bool), T) {
+        if !not_busted(&count_sides(&sample.present())) {
+            return ((DiceSetSample::default(), false), -T::from_u32(current_score.score()));
+        }
+        let mut best_decision = (best_selection(sample.clone()), false);
+        let mut best = T::from_u32(best_score(count_sides(&sample.present())).score());
+        for selection in sample.iter_selections() {
+            let value = self.query_decision_value(current_score, sample, &selection, true);
+            if value > best {
+                best = value;
+                best_decision = (selection, true);
+            }
+        }
+        return (best_decision, best);
+    }
+
+    /// Lays out every valid selection of the given sample with what banking or rolling again after it is
+    /// expected to gain, best first. Values are worked out the same way as query_best_decision().
+    ///
+    /// Keeping the same faces with the same outcome from different dice is only listed once. A sample that
+    /// has gone bust has no options.
+    pub fn explain_decision(&self, current_score: FarkleScore, sample: &DiceSetSample) -> DecisionExplanation<T> {
+        return explain(
+            &self.dices, current_score, sample,
+            |remaining| self.bust_prob[remaining].clone(),
+            |select_score, remaining| self.hold_value(current_score, select_score, remaining),
+        );
+    }
+
+    /// Returns the largest difference between the expected scores of this strategy and another (computed with
+    /// the same dice and domain but potentially a different number type). Useful for measuring floating point drift.
+    pub fn max_difference<U: Numeric>(&self, other: &OptimalStrat<U>) -> f64 {
+        assert_eq!(self.domain(), other.domain(), "Strategies use different score domains");
+        return self.expected_scores.iter()
+            .zip(other.expected_scores.iter())
+            .map(|((_, a), (_, b))| (a.to_f64() - b.to_f64()).abs())
+            .fold(0.0, f64::max);
+    }
+
     /// Computes the expected score for the Optimal_n+1 strategy
     pub fn iterate(&self) -> Self {
-        let mut expected_scores = PerfectHashMap::<(FarkleScore, [bool; 6]), f32>::new();
+        let domain = self.domain();
+        let mut expected_scores = ScoreMap::<[bool; 6], T>::new(domain);
+        let set_size = expected_scores.set_size() as u64;
         let expected_hold = self.iterate_hold(&self.dices);
         // For all possible score and dice subset product combinations
-        expected_scores.iter_mut()
-        .par_bridge()
-        .progress_count(<(FarkleScore, [bool; 6])>::SET_SIZE as u64)
-        .for_each(|((p, selection), dataslot)| {
+        for_each_progress(expected_scores.iter_mut(), set_size, |((p, selection), dataslot)| {
             // Calculate expected loss from busting
-            let expected_bust_loss = p.score() as f32 * self.bust_prob[selection];
+            let expected_bust_loss = T::from_u32(p.score()) * self.bust_prob[selection].clone();
             // Calculate expected gain when not busting
-            let mut expected_score_gain = 0.0;
+            let mut expected_score_gain = T::zero();
             let diceset = DiceSet::new(&self.dices, selection);
             for (sample_wrapped, prob) in diceset.iter_outcomes() {
                 // Calculate terminate decision payoff
                 let sample = sample_wrapped.sample.iter().filter_map(|&o| o).collect::<Vec<_>>();
-                let terminate = best_score(count_sides(&sample)).score() as f32;
+                let terminate = T::from_u32(best_score(count_sides(&sample)).score());
                 // Calculate hold decision payoff
-                let (hold, _) = expected_hold[(p, sample_wrapped)];
+                let hold = expected_hold[(p, sample_wrapped)].0.clone();
                 // Calculate higher of a and b and update expectated score
-                expected_score_gain += prob * terminate.max(hold);
+                if hold > terminate {
+                    expected_score_gain += prob * hold;
+                } else {
+                    expected_score_gain += prob * terminate;
+                }
             }
             *dataslot = expected_score_gain - expected_bust_loss;
         });
         // Having no dice means the player loops back round to 6-dice. Thus for any farkle score P,
         // it should be that Optimal(P, no_dice)=Optimal(P, dice)
-        for p in (0..FarkleScore::SET_SIZE).map(|h| FarkleScore::from_perfhash(PerfectHash::new(h))) {
-            expected_scores[(p, [false; 6])] = expected_scores[(p, [true; 6])];
+        for p in domain.scores() {
+            expected_scores[(p, [false; 6])] = expected_scores[(p, [true; 6])].clone();
         }
-        return Self {expected_scores, expected_hold, dices: self.dices.clone(), bust_prob: self.bust_prob.clone(), n: self.n + 1};
+        return Self {expected_scores, expected_hold, dices: self.dices.clone(), bust_prob: self.bust_prob.clone(), n: self.n + 1, format: CheckpointFormat};
     }
 
     /// Computes the expected payoff for the "Hold" decision for Optimal_n+1
-    fn iterate_hold(&self, dices: &[Dice; 6]) -> PerfectHashMap<(FarkleScore, DiceSetSample), (f32, DiceSetSample)> {
-        let mut hold: PerfectHashMap<(FarkleScore, DiceSetSample), (f32, DiceSetSample)> = PerfectHashMap::new();
-        hold.iter_mut()
-        .par_bridge()
-        .progress_count(<(FarkleScore, DiceSetSample)>::SET_SIZE as u64)
+    fn iterate_hold(&self, dices: &[Dice<T>; 6]) -> ScoreMap<DiceSetSample, (T, DiceSetSample)> {
+        let mut hold: ScoreMap<DiceSetSample, (T, DiceSetSample)> = ScoreMap::new(self.domain());
+        let set_size = hold.set_size() as u64;
         // For all possible scores and dice samples
-        .for_each(|((current_score, sample_wrapped), (expected_gain, selection))| {
-            let mut best_gain: f32 = 0.0;
+        for_each_progress(hold.iter_mut(), set_size, |((current_score, sample_wrapped), (expected_gain, selection))| {
+            let mut best_gain = T::zero();
             let mut best_selection = DiceSetSample::default();
             // For all possible selections of a sample
             for selection in sample_wrapped.iter_selections() {
@@ -133,13 +266,9 @@ impl OptimalStrat {
                 }
                 let selected_dice = DiceSet::new(dices, selection.present_mask());
                 let unselected_dice = selected_dice.complement();
-                // Calculate "terminate" decision payoff
-                let optimal_score = self.expected_scores[(
-                    FarkleScore::new((current_score.score() + select_score).clamp(0, 5950)),
-                    unselected_dice.select_mask
-                )];
+                // Calculate payoff of rolling the rest of the dice
+                let total = self.hold_value(current_score, select_score, unselected_dice.select_mask);
                 // Store the highest payoff so far
-                let total = select_score as f32 + optimal_score;
                 if total > best_gain {
                     best_gain = total;
                     best_selection = selection;
@@ -152,13 +281,13 @@ impl OptimalStrat {
     }
 
     /// Calculates Farkle busting probabilities for 6 given die
-    fn generate_busting_probabilities(dices: &[Dice; 6]) -> PerfectHashMap<[bool; 6], f32> {
+    fn generate_busting_probabilities(dices: &[Dice<T>; 6]) -> PerfectHashMap<[bool; 6], T> {
         let mut data = PerfectHashMap::new();
         let entire_set = DiceSet::new(dices, [true; 6]);
         // For each possible dice subset
         for subset in entire_set.iter_subsets() {
             // Calculate probability of busting
-            let mut bust_prob = 0.0;
+            let mut bust_prob = T::zero();
             for (sample_wrapped, prob) in subset.iter_outcomes() {
                 if !not_busted(&count_sides(&sample_wrapped.present())) {
                     bust_prob += prob;
@@ -168,7 +297,350 @@ impl OptimalStrat {
             data[subset.select_mask] = bust_prob;
         }
         // Having no dice means the player loops back round to 6-dice...
-        data[[false; 6]] = data[[true; 6]];
+        data[[false; 6]] = data[[true; 6]].clone();
         return data;
     }
 }
+impl OptimalStrat {
+    /// Saves the tables of this strategy into the given directory so they can be opened with MappedStrat::open()
+    pub fn save_tables(&self, dir: impl AsRef<Path>) -> io::Result<()> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+        self.expected_scores.save_flat(dir.join("expected_scores.bin"))?;
+        self.expected_hold.save_flat(dir.join("expected_hold.bin"))?;
+        self.bust_prob.save_flat(dir.join("bust_prob.bin"))?;
+        let meta = TableMeta {dices: self.dices, n: self.n, domain: self.domain()};
+        std::fs::write(dir.join("meta.json"), serde_json::to_string(&meta)?)?;
+        return Ok(());
+    }
+}
+
+/// One way of playing a roll, see OptimalStrat::explain_decision()
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DecisionOption<T = f32> {
+    /// The dice kept
+    pub selection: DiceSetSample,
+    /// Points scored by the dice kept
+    pub score: u32,
+    /// Dice left to roll. None left means all 6 are rolled again.
+    pub remaining: [bool; 6],
+    /// Probability of the next roll going bust if rolling again
+    pub bust_prob: T,
+    /// Expected score gain of keeping the selection and banking
+    pub bank_value: T,
+    /// Expected score gain of keeping the selection and rolling the remaining dice
+    pub roll_value: T,
+}
+impl<T: Numeric> DecisionOption<T> {
+    /// Whether rolling again is better than banking. Ties are banked.
+    pub fn roll_again(&self) -> bool {
+        return self.roll_value > self.bank_value;
+    }
+
+    /// Expected score gain of the better of banking and rolling again
+    pub fn value(&self) -> T {
+        if self.roll_again() {
+            return self.roll_value.clone();
+        }
+        return self.bank_value.clone();
+    }
+}
+
+/// Every option for a roll, best first
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DecisionExplanation<T = f32> {
+    pub current_score: FarkleScore,
+    pub options: Vec<DecisionOption<T>>,
+}
+impl<T: Numeric> DecisionExplanation<T> {
+    /// The best option. None if the roll went bust.
+    pub fn best(&self) -> Option<&DecisionOption<T>> {
+        return self.options.first();
+    }
+
+    /// How much more the best option is expected to gain than the next best, whether that is the
+    /// best option's other choice (banking vs rolling) or a different selection. None if the roll went bust.
+    pub fn margin(&self) -> Option<T> {
+        let best = self.best()?;
+        let mut runner_up = if best.roll_again() { best.bank_value.clone() } else { best.roll_value.clone() };
+        if let Some(second) = self.options.get(1) && second.value() > runner_up {
+            runner_up = second.value();
+        }
+        return Some(best.value() - runner_up);
+    }
+}
+impl<T: Numeric> fmt::Display for DecisionExplanation<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.options.is_empty() {
+            return writeln!(f, "Bust, losing {}", self.current_score.score());
+        }
+        writeln!(f, "Keep   | Score | Dice left | Bust prob |     Bank |     Roll | Choice")?;
+        for option in self.options.iter() {
+            writeln!(
+                f, "{:<6} | {:>5} | {:>9} | {:>9.4} | {:>8.2} | {:>8.2} | {}",
+                option.selection.to_string(), option.score, dice_left(&option.remaining),
+                option.bust_prob.to_f64(), option.bank_value.to_f64(), option.roll_value.to_f64(),
+                if option.roll_again() { "roll" } else { "bank" },
+            )?;
+        }
+        if let Some(margin) = self.margin() {
+            writeln!(f, "Best option wins by {:.2}", margin.to_f64())?;
+        }
+        return Ok(());
+    }
+}
+
+/// Lays out the options of a sample, see OptimalStrat::explain_decision(). roll_value gives the expected score
+/// gain of holding a selection worth the given points and rolling the remaining dice.
+fn explain<T: Numeric>(
+    dices: &[Dice<T>; 6], current_score: FarkleScore, sample: &DiceSetSample,
+    bust_prob: impl Fn([bool; 6]) -> T, roll_value: impl Fn(u32, [bool; 6]) -> T,
+) -> DecisionExplanation<T> {
+    let mut options = Vec::new();
+    for selection in sample.iter_selections() {
+        let select_score = score(count_sides(&selection.present())).score();
+        // Skip any selections that form invalid hands
+        if select_score == 0 {
+            continue;
+        }
+        let remaining = DiceSet::new(dices, sample.present_mask()).new_subset(&selection.present_mask().map(|b| !b)).select_mask;
+        let option = DecisionOption {
+            bust_prob: bust_prob(remaining),
+            bank_value: T::from_u32(select_score),
+            roll_value: roll_value(select_score, remaining),
+            selection,
+            score: select_score,
+            remaining,
+        };
+        // Keeping the same faces from different (but identical) dice is the same option
+        let faces = count_sides(&option.selection.present());
+        let duplicate = options.iter().any(|other: &DecisionOption<T>| {
+            return count_sides(&other.selection.present()) == faces && other.bust_prob == option.bust_prob && other.roll_value == option.roll_value;
+        });
+        if !duplicate {
+            options.push(option);
+        }
+    }
+    // Stable sort so ties keep the order selections were generated in
+    options.sort_by(|a, b| b.value().partial_cmp(&a.value()).unwrap_or(std::cmp::Ordering::Equal));
+    return DecisionExplanation {current_score, options};
+}
+
+/// Number of dice rolled next, where having none left means rolling all 6 again
+fn dice_left(remaining: &[bool; 6]) -> usize {
+    let n = remaining.iter().filter(|&&b| b).count();
+    if n == 0 {
+        return 6;
+    }
+    return n;
+}
+
+/// Small details of a strategy saved alongside its tables
+#[derive(Debug, Serialize, Deserialize)]
+struct TableMeta {
+    dices: [Dice; 6],
+    n: usize,
+    /// Tables saved before the domain was configurable always used the default domain
+    #[serde(default)]
+    domain: ScoreDomain,
+}
+
+/// Read-only version of OptimalStrat backed by flat tables (see OptimalStrat::save_tables()).
+///
+/// Tables are memory mapped by default (except on wasm, see TableBytes), so opening is instant and
+/// processes using the same tables share memory.
+pub struct MappedStrat<D: Deref<Target = [u8]> = TableBytes> {
+    /// Expected score gain values for all possible scores and die subsets
+    pub expected_scores: FlatScoreMap<[bool; 6], f32, D>,
+    /// Expected score gain values (and the dice to select) when definitely going to "Hold"
+    expected_hold: FlatScoreMap<DiceSetSample, (f32, DiceSetSample), D>,
+    /// Busting probabilities of the dices
+    pub bust_prob: FlatPerfectHashMap<[bool; 6], f32, D>,
+    /// The die weightings this strategy is based on
+    pub dices: [Dice; 6],
+    /// Number of rolls until the "Terminate" strategy must be used
+    pub n: usize,
+}
+impl MappedStrat<TableBytes> {
+    /// Opens the tables saved into the given directory by OptimalStrat::save_tables()
+    pub fn open(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = dir.as_ref();
+        let meta: TableMeta = serde_json::from_str(&std::fs::read_to_string(dir.join("meta.json"))?)?;
+        return Ok(Self {
+            expected_scores: FlatScoreMap::open(dir.join("expected_scores.bin"), meta.domain)?,
+            expected_hold: FlatScoreMap::open(dir.join("expected_hold.bin"), meta.domain)?,
+            bust_prob: FlatPerfectHashMap::open(dir.join("bust_prob.bin"))?,
+            dices: meta.dices,
+            n: meta.n,
+        });
+    }
+}
+impl<D: Deref<Target = [u8]>> MappedStrat<D> {
+    /// Creates the strategy from the raw bytes of each table. The domain must be the one the tables
+    /// were saved with.
+    pub fn from_bytes(expected_scores: D, expected_hold: D, bust_prob: D, dices: [Dice; 6], n: usize, domain: ScoreDomain) -> io::Result<Self> {
+        return Ok(Self {
+            expected_scores: FlatScoreMap::from_bytes(expected_scores, domain)?,
+            expected_hold: FlatScoreMap::from_bytes(expected_hold, domain)?,
+            bust_prob: FlatPerfectHashMap::from_bytes(bust_prob)?,
+            dices,
+            n,
+        });
+    }
+
+    /// Creates the strategy from the contents of each file written by OptimalStrat::save_tables()
+    pub fn from_table_bytes(meta: &str, expected_scores: D, expected_hold: D, bust_prob: D) -> io::Result<Self> {
+        let meta: TableMeta = serde_json::from_str(meta)?;
+        return Self::from_bytes(expected_scores, expected_hold, bust_prob, meta.dices, meta.n, meta.domain);
+    }
+
+    /// The range of scores this strategy keeps track of
+    pub fn domain(&self) -> ScoreDomain {
+        return self.expected_scores.domain();
+    }
+
+    /// Returns the expected score of this strategy with the given current score and boolean mask of dice left
+    pub fn query_score(&self, score: FarkleScore, die: [bool; 6]) -> f32 {
+        return self.expected_scores.get(&(score, die)).expect("Score out of range");
+    }
+
+    /// Returns the decision used by this strategy with the given current score and dice sample.
+    /// Same as OptimalStrat::query_decision().
+    pub fn query_decision(&self, score: FarkleScore, sample: DiceSetSample) -> (DiceSetSample, bool) {
+        let terminate = best_score(count_sides(&sample.present())).score() as f32;
+        let (hold, hold_selection) = self.expected_hold.get(&(score, sample.clone())).expect("Score out of range");
+        if terminate >= hold {
+            return (best_selection(sample), false);
+        }
+        return (hold_selection, true);
+    }
+
+    /// Lays out every valid selection of the given sample, best first. Same as OptimalStrat::explain_decision().
+    pub fn explain_decision(&self, current_score: FarkleScore, sample: &DiceSetSample) -> DecisionExplanation {
+        return explain(
+            &self.dices, current_score, sample,
+            |remaining| self.bust_prob.get(&remaining).expect("Masks are always in range"),
+            |select_score, remaining| {
+                let next_score = current_score.score() + select_score;
+                if next_score >= self.domain().cap() {
+                    return select_score as f32;
+                }
+                return select_score as f32 + self.query_score(FarkleScore::new(next_score), remaining);
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::farkle::DiceSide;
+    use num_rational::BigRational;
+
+    /// Sample of all 6 dice where the only scoring dice is a single 1
+    fn single_one() -> DiceSetSample {
+        let sides = [DiceSide::One, DiceSide::Two, DiceSide::Three, DiceSide::Four, DiceSide::Six, DiceSide::Six];
+        return DiceSetSample {sample: sides.map(Some)};
+    }
+
+    #[test]
+    fn reaching_cap_ends_round() {
+        let dices: [Dice; 6] = [Dice::default(); 6];
+        let sample = single_one();
+        let keep_one = best_selection(sample.clone());
+        let near_cap = OptimalStrat::with_domain(dices, ScoreDomain::new(50, 1000).unwrap());
+        let far_from_cap = OptimalStrat::with_domain(dices, ScoreDomain::new(50, 1500).unwrap());
+
+        // Keeping the 1 at 900 reaches the cap, so rolling again gains nothing more and banking is best
+        assert_eq!(near_cap.query_decision_value(FarkleScore::new(900), &sample, &keep_one, true), 100.0);
+        let ((selection, roll_again), value) = near_cap.query_best_decision(FarkleScore::new(900), &sample);
+        assert_eq!((selection, roll_again, value), (keep_one.clone(), false, 100.0));
+
+        // Below the cap the same roll is worth gambling on
+        let ((selection, roll_again), value) = far_from_cap.query_best_decision(FarkleScore::new(900), &sample);
+        assert_eq!((selection.clone(), roll_again), (keep_one.clone(), true));
+        assert_eq!(value, 100.0 + far_from_cap.query_score(FarkleScore::new(1000), [false, true, true, true, true, true]));
+        assert!(value > 100.0);
+    }
+
+    #[test]
+    fn hold_table_respects_cap() {
+        let dices: [Dice; 6] = [Dice::default(); 6];
+        let sample = single_one();
+        let optimal = OptimalStrat::with_domain(dices, ScoreDomain::new(50, 200).unwrap()).iterate();
+        // Reaching the cap is a win so the round is banked
+        assert_eq!(optimal.query_decision(FarkleScore::new(100), sample.clone()), (best_selection(sample.clone()), false));
+        // Whereas from 0 it's worth rolling the other 5 dice
+        assert_eq!(optimal.query_decision(FarkleScore::new(0), sample.clone()), (best_selection(sample), true));
+    }
+
+    #[test]
+    fn explain_decision() {
+        let dices: [Dice; 6] = [Dice::default(); 6];
+        let optimal = OptimalStrat::with_domain(dices, ScoreDomain::new(50, 1000).unwrap());
+        let five_dice = [false, true, true, true, true, true];
+        let explanation = optimal.explain_decision(FarkleScore::new(0), &single_one());
+        assert_eq!(explanation.options.len(), 1);
+        let keep_one = &explanation.options[0];
+        assert_eq!((keep_one.score, keep_one.remaining, keep_one.bank_value), (100, five_dice, 100.0));
+        assert_eq!(keep_one.bust_prob, optimal.bust_prob[five_dice]);
+        assert_eq!(keep_one.roll_value, 100.0 + optimal.query_score(FarkleScore::new(100), five_dice));
+
+        let sample = DiceSetSample::from_faces([1, 5, 5, 2, 3, 6]).unwrap();
+        let explanation = optimal.explain_decision(FarkleScore::new(300), &sample);
+        // 1, 5, 15, 55 and 155 (the dice are identical so it doesn't matter which 5 is kept)
+        assert_eq!(explanation.options.len(), 5);
+        assert!(explanation.options.windows(2).all(|w| w[0].value() >= w[1].value()));
+        assert_eq!(explanation.best().unwrap().value(), optimal.query_best_decision(FarkleScore::new(300), &sample).1);
+        assert!(explanation.margin().unwrap() >= 0.0);
+        assert!(explanation.to_string().contains("Best option wins by"));
+
+        let bust = optimal.explain_decision(FarkleScore::new(300), &DiceSetSample::from_faces([2, 3, 4, 6, 6, 2]).unwrap());
+        assert_eq!((bust.options.len(), bust.margin()), (0, None));
+        assert_eq!(bust.to_string(), "Bust, losing 300\n");
+    }
+
+    #[test]
+    #[ignore = "solves the full default domain, run with cargo test --release -- --ignored"]
+    fn default_round_scores() {
+        // The payoffs quoted in the README findings
+        let optimal: OptimalStrat = OptimalStrat::new([Dice::default(); 6]);
+        assert!((optimal.query_score(FarkleScore::new(0), [true; 6]) - 399.04).abs() < 1e-2);
+        let optimal = optimal.iterate();
+        assert_eq!(optimal.n, 2);
+        assert!((optimal.query_score(FarkleScore::new(0), [true; 6]) - 482.447).abs() < 1e-2);
+    }
+
+    #[test]
+    fn checkpoint_format() {
+        let optimal: OptimalStrat = OptimalStrat::with_domain([Dice::default(); 6], ScoreDomain::new(50, 100).unwrap());
+        let mut json = serde_json::to_value(&optimal).unwrap();
+        assert_eq!(json["format"], CHECKPOINT_FORMAT);
+        let loaded: OptimalStrat = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(loaded.query_score(FarkleScore::new(50), [true; 6]), optimal.query_score(FarkleScore::new(50), [true; 6]));
+
+        json["format"] = (CHECKPOINT_FORMAT + 1).into();
+        let err = serde_json::from_value::<OptimalStrat>(json.clone()).unwrap_err();
+        assert!(err.to_string().contains("is not supported"));
+        // Checkpoints from before the format was added
+        json.as_object_mut().unwrap().remove("format");
+        let err = serde_json::from_value::<OptimalStrat>(json).unwrap_err();
+        assert!(err.to_string().contains("missing field `format`"));
+    }
+
+    #[test]
+    fn exact_rationals() {
+        let one_die = [true, false, false, false, false, false];
+        let two_dice = [true, true, false, false, false, false];
+        let dices: [Dice<BigRational>; 6] = [(); 6].map(|_| Dice::default());
+        let optimal = OptimalStrat::with_domain(dices, ScoreDomain::new(50, 50).unwrap());
+        // A single die busts on 2, 3, 4 or 6 and otherwise scores 100 or 50
+        assert_eq!(optimal.bust_prob[one_die], BigRational::ratio(2, 3));
+        assert_eq!(optimal.bust_prob[two_dice], BigRational::ratio(4, 9));
+        assert_eq!(optimal.query_score(FarkleScore::new(0), one_die), BigRational::from_u32(25));
+        assert_eq!(optimal.query_score(FarkleScore::new(0), two_dice), BigRational::from_u32(50));
+
+        let floats = OptimalStrat::with_domain([Dice::<f64>::default(); 6], ScoreDomain::new(50, 50).unwrap());
+        assert!(optimal.max_difference(&floats) < 1e-9);
+    }
+}
@@ -0,0 +1,55 @@
+//! Loops that run across threads with a progress bar when the parallel feature is on, and run
+//! one after another otherwise (e.g on wasm32 where there are no threads).
+
+#[cfg(feature = "parallel")]
+use indicatif::ParallelProgressIterator;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Calls body on every item of iter, showing progress out of len
+#[cfg(feature = "parallel")]
+pub(crate) fn for_each_progress<I, F>(iter: I, len: u64, body: F)
+where
+    I: Iterator + Send,
+    I::Item: Send,
+    F: Fn(I::Item) + Sync + Send,
+{
+    iter.par_bridge().progress_count(len).for_each(body);
+}
+
+/// Calls body on every item of iter
+#[cfg(not(feature = "parallel"))]
+pub(crate) fn for_each_progress<I, F>(iter: I, _len: u64, body: F)
+where
+    I: Iterator + Send,
+    I::Item: Send,
+    F: Fn(I::Item) + Sync + Send,
+{
+    iter.for_each(body);
+}
+
+/// Maps every item (along with its index) keeping the order of items. Shows progress if asked to.
+#[cfg(feature = "parallel")]
+pub(crate) fn map_indexed<T, R, F>(items: &[T], progress: bool, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(usize, &T) -> R + Sync + Send,
+{
+    let iter = items.par_iter().enumerate();
+    if progress {
+        return iter.progress_count(items.len() as u64).map(|(i, item)| f(i, item)).collect();
+    }
+    return iter.map(|(i, item)| f(i, item)).collect();
+}
+
+/// Maps every item (along with its index) keeping the order of items
+#[cfg(not(feature = "parallel"))]
+pub(crate) fn map_indexed<T, R, F>(items: &[T], _progress: bool, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(usize, &T) -> R + Sync + Send,
+{
+    return items.iter().enumerate().map(|(i, item)| f(i, item)).collect();
+}
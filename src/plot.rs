@@ -0,0 +1,172 @@
+//! SVG heatmaps of a solved strategy (built from a FindingsReport) for including in reports.
+//!
+//! Turn score runs along the x axis and the number of dice remaining up the y axis.
+
+use crate::findings::FindingsReport;
+use std::fmt::Write;
+
+const PLOT_WIDTH: f32 = 720.0;
+const CELL_HEIGHT: f32 = 36.0;
+const MARGIN_LEFT: f32 = 70.0;
+const MARGIN_TOP: f32 = 40.0;
+const MARGIN_BOTTOM: f32 = 50.0;
+const MARGIN_RIGHT: f32 = 130.0;
+const MAX_TICKS: usize = 12;
+
+const NEGATIVE: [u8; 3] = [0xb2, 0x18, 0x2b];
+const NEUTRAL: [u8; 3] = [0xf7, 0xf7, 0xf7];
+const POSITIVE: [u8; 3] = [0x21, 0x66, 0xac];
+const BANK: [u8; 3] = [0xd6, 0x60, 0x4d];
+const ROLL: [u8; 3] = [0x43, 0x93, 0xc3];
+
+/// Heatmap of the expected score gain of rolling, by turn score and number of dice remaining. Losses
+/// are red, gains are blue and breaking even is white.
+pub fn expected_gain_svg(report: &FindingsReport) -> String {
+    let (min, max) = report.expected_scores.iter().flatten()
+        .fold((0.0f32, 0.0f32), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+    let mut out = open_svg(report, "Expected score gain of rolling");
+    draw_cells(&mut out, report, |v| diverging(v, min, max));
+    // Colour scale drawn as a stack of small blocks from max (top) to min (bottom)
+    let x = MARGIN_LEFT + PLOT_WIDTH + 20.0;
+    let height = 6.0 * CELL_HEIGHT;
+    let steps = 48;
+    for i in 0..steps {
+        let v = max - (max - min) * (i as f32 + 0.5) / steps as f32;
+        let y = MARGIN_TOP + height * i as f32 / steps as f32;
+        writeln!(out, r#"<rect x="{:.2}" y="{:.2}" width="16" height="{:.2}" fill="{}"/>"#, x, y, height / steps as f32 + 0.5, diverging(v, min, max)).unwrap();
+    }
+    let label_x = x + 22.0;
+    let value_y = |v: f32| MARGIN_TOP + height * (max - v) / (max - min).max(f32::EPSILON);
+    let mut labels = vec![max, min];
+    if min < 0.0 && max > 0.0 {
+        labels.push(0.0);
+    }
+    for v in labels {
+        writeln!(out, r#"<text x="{:.2}" y="{:.2}" dominant-baseline="middle">{:.0}</text>"#, label_x, value_y(v), v).unwrap();
+    }
+    out += "</svg>\n";
+    return out;
+}
+
+/// Map of where rolling the remaining dice is expected to gain points (roll) or lose them (bank), by
+/// turn score and number of dice remaining
+pub fn decision_regions_svg(report: &FindingsReport) -> String {
+    let mut out = open_svg(report, "Bank or roll the remaining dice");
+    draw_cells(&mut out, report, |v| hex(if v > 0.0 { ROLL } else { BANK }));
+    let x = MARGIN_LEFT + PLOT_WIDTH + 20.0;
+    for (i, (name, colour)) in [("Roll", ROLL), ("Bank", BANK)].into_iter().enumerate() {
+        let y = MARGIN_TOP + 24.0 * i as f32;
+        writeln!(out, r#"<rect x="{:.2}" y="{:.2}" width="16" height="16" fill="{}"/>"#, x, y, hex(colour)).unwrap();
+        writeln!(out, r#"<text x="{:.2}" y="{:.2}" dominant-baseline="middle">{}</text>"#, x + 22.0, y + 8.0, name).unwrap();
+    }
+    out += "</svg>\n";
+    return out;
+}
+
+/// Starts the SVG document with its title and axes
+fn open_svg(report: &FindingsReport, title: &str) -> String {
+    let width = MARGIN_LEFT + PLOT_WIDTH + MARGIN_RIGHT;
+    let height = MARGIN_TOP + 6.0 * CELL_HEIGHT + MARGIN_BOTTOM;
+    let mut out = String::new();
+    writeln!(out, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.0}" height="{:.0}" font-family="sans-serif" font-size="12">"#, width, height).unwrap();
+    writeln!(out, r#"<rect width="100%" height="100%" fill="white"/>"#).unwrap();
+    writeln!(out, r#"<text x="{:.2}" y="24" text-anchor="middle" font-size="16">{} (Optimal_{})</text>"#, MARGIN_LEFT + PLOT_WIDTH / 2.0, title, report.n).unwrap();
+    // Dice remaining, 6 at the top
+    for k in 1..=6 {
+        let y = row_y(k) + CELL_HEIGHT / 2.0;
+        writeln!(out, r#"<text x="{:.2}" y="{:.2}" text-anchor="end" dominant-baseline="middle">{}</text>"#, MARGIN_LEFT - 8.0, y, k).unwrap();
+    }
+    writeln!(
+        out, r#"<text transform="translate(20 {:.2}) rotate(-90)" text-anchor="middle">Dice remaining</text>"#,
+        MARGIN_TOP + 3.0 * CELL_HEIGHT,
+    ).unwrap();
+    // Turn scores, spaced out so the labels don't overlap
+    let bottom = MARGIN_TOP + 6.0 * CELL_HEIGHT;
+    let cell_width = PLOT_WIDTH / report.scores.len() as f32;
+    let tick_every = report.scores.len().div_ceil(MAX_TICKS).max(1);
+    for (i, p) in report.scores.iter().enumerate().step_by(tick_every) {
+        let x = MARGIN_LEFT + cell_width * (i as f32 + 0.5);
+        writeln!(out, r#"<line x1="{:.2}" y1="{:.2}" x2="{:.2}" y2="{:.2}" stroke="black"/>"#, x, bottom, x, bottom + 4.0).unwrap();
+        writeln!(out, r#"<text x="{:.2}" y="{:.2}" text-anchor="middle">{}</text>"#, x, bottom + 18.0, p).unwrap();
+    }
+    writeln!(out, r#"<text x="{:.2}" y="{:.2}" text-anchor="middle">Turn score</text>"#, MARGIN_LEFT + PLOT_WIDTH / 2.0, bottom + 40.0).unwrap();
+    return out;
+}
+
+/// Draws a cell for every score and number of dice, coloured by its expected score gain
+fn draw_cells(out: &mut String, report: &FindingsReport, colour: impl Fn(f32) -> String) {
+    let cell_width = PLOT_WIDTH / report.scores.len() as f32;
+    for (i, row) in report.expected_scores.iter().enumerate() {
+        for (k, &v) in (1..=6).zip(row.iter()) {
+            // Overlap slightly so no gaps show between cells
+            writeln!(
+                out, r#"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" fill="{}"><title>{} with {} dice: {:.2}</title></rect>"#,
+                MARGIN_LEFT + cell_width * i as f32, row_y(k), cell_width + 0.5, CELL_HEIGHT, colour(v), report.scores[i], k, v,
+            ).unwrap();
+        }
+    }
+}
+
+/// Top of the row for the given number of dice
+fn row_y(dice: usize) -> f32 {
+    return MARGIN_TOP + (6 - dice) as f32 * CELL_HEIGHT;
+}
+
+/// Colour of a value on a scale from min (red) through 0 (white) to max (blue)
+fn diverging(v: f32, min: f32, max: f32) -> String {
+    if v < 0.0 && min < 0.0 {
+        return hex(lerp(NEUTRAL, NEGATIVE, v / min));
+    }
+    if v > 0.0 && max > 0.0 {
+        return hex(lerp(NEUTRAL, POSITIVE, v / max));
+    }
+    return hex(NEUTRAL);
+}
+
+fn lerp(a: [u8; 3], b: [u8; 3], t: f32) -> [u8; 3] {
+    let t = t.clamp(0.0, 1.0);
+    return std::array::from_fn(|i| (a[i] as f32 + (b[i] as f32 - a[i] as f32) * t).round() as u8);
+}
+
+fn hex(colour: [u8; 3]) -> String {
+    return format!("#{:02x}{:02x}{:02x}", colour[0], colour[1], colour[2]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::threshold::ThresholdReport;
+
+    fn report() -> FindingsReport {
+        let scores: Vec<u32> = (0..4).map(|i| i * 50).collect();
+        let expected_scores = vec![
+            [25.0, 60.0, 110.0, 170.0, 250.0, 390.0],
+            [-8.0, 30.0, 90.0, 160.0, 240.0, 385.0],
+            [-42.0, 5.0, 70.0, 150.0, 235.0, 380.0],
+            [-75.0, -20.0, 50.0, 140.0, 230.0, 375.0],
+        ];
+        return FindingsReport {n: 2, scores, expected_scores, bust_prob: Vec::new(), thresholds: ThresholdReport {thresholds: Vec::new()}};
+    }
+
+    #[test]
+    fn colour_scale() {
+        assert_eq!(diverging(0.0, -10.0, 10.0), hex(NEUTRAL));
+        assert_eq!(diverging(-10.0, -10.0, 10.0), hex(NEGATIVE));
+        assert_eq!(diverging(20.0, -10.0, 10.0), hex(POSITIVE));
+        assert_eq!(diverging(5.0, 0.0, 10.0), "#8cafd2");
+    }
+
+    #[test]
+    fn heatmaps() {
+        let report = report();
+        let gain = expected_gain_svg(&report);
+        assert!(gain.starts_with("<svg") && gain.ends_with("</svg>\n"));
+        assert_eq!(gain.matches("<title>").count(), 4 * 6);
+        assert!(gain.contains("<title>50 with 1 dice: -8.00</title>"));
+        assert!(gain.contains(">Turn score<") && gain.contains(">Dice remaining<"));
+
+        let regions = decision_regions_svg(&report);
+        assert_eq!(regions.matches(&format!(r#"fill="{}"><title>"#, hex(BANK))).count(), 4);
+        assert_eq!(regions.matches(&format!(r#"fill="{}"><title>"#, hex(ROLL))).count(), 4 * 6 - 4);
+    }
+}
@@ -0,0 +1,220 @@
+//! Compact decision-only tables for advisors that don't need any expected score values.
+//!
+//! Rolls are keyed by the faces shown (sorted, so as a multiset) rather than which die shows them.
+//! This only loses information when the dice differ, so exporting requires every die to be the same.
+//!
+//! File layout:
+//!  - 8 byte magic ("KCDPOLI" followed by a format version byte)
+//!  - Score granularity and score cap, each a little-endian u32
+//!  - A decision byte for every score of the domain (ascending), then for every roll (see roll_index())
+//!
+//! Decision bytes have bit i set when the i-th smallest face rolled is kept, and bit 6 set when
+//! rolling again afterwards. With the default domain this is 120 * 923 bytes, about 110KB.
+//!
+//! PolicyReader (along with roll_index()) only relies on std, so it can be lifted into an advisor on its own.
+
+use crate::farkle::{count_sides, DiceSetSample, DiceSide};
+use crate::numeric::Numeric;
+use crate::optimal::OptimalStrat;
+use std::fmt;
+use std::io;
+use std::ops::Deref;
+use std::path::Path;
+
+const MAGIC: &[u8; 8] = b"KCDPOLI\x01";
+const HEADER_SIZE: usize = 16;
+const ROLL_AGAIN_BIT: u8 = 1 << 6;
+/// Number of distinct rolls of 1 to 6 dice when only the faces shown matter
+pub const ROLL_COUNT: usize = 923;
+
+fn binomial(n: usize, k: usize) -> usize {
+    if k > n {
+        return 0;
+    }
+    return (0..k).fold(1, |acc, i| acc * (n - i) / (i + 1));
+}
+
+/// Index of a roll given its faces (1 to 6) in ascending order. Rolls of fewer dice come first, then
+/// rolls of the same number of dice are ranked with the combinatorial number system.
+fn roll_index(sorted: &[u8]) -> usize {
+    // There are C(j+5, 5) rolls of j dice
+    let offset: usize = (1..sorted.len()).map(|j| binomial(j + 5, 5)).sum();
+    // Spreading the faces out by their position makes them strictly increasing, which can then be ranked
+    let rank: usize = sorted.iter().enumerate()
+        .map(|(i, &face)| binomial(face as usize - 1 + i, i + 1))
+        .sum();
+    return offset + rank;
+}
+
+/// Reasons a strategy can't be exported as a policy table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyError {
+    /// The dice aren't all the same, so decisions depend on more than the faces rolled
+    DiceDiffer,
+}
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DiceDiffer => return write!(f, "policy tables need every die to be the same"),
+        }
+    }
+}
+impl std::error::Error for PolicyError {}
+
+/// Encodes every decision of the strategy as a policy table
+pub fn export_policy<T: Numeric>(optimal: &OptimalStrat<T>) -> Result<Vec<u8>, PolicyError> {
+    let first = &optimal.dices[0];
+    if !optimal.dices.iter().all(|dice| (0..6).all(|side| dice[side] == first[side])) {
+        return Err(PolicyError::DiceDiffer);
+    }
+    let domain = optimal.domain();
+    let mut out = vec![0u8; HEADER_SIZE + domain.len() * ROLL_COUNT];
+    out[..8].copy_from_slice(MAGIC);
+    out[8..12].copy_from_slice(&domain.granularity().to_le_bytes());
+    out[12..16].copy_from_slice(&domain.cap().to_le_bytes());
+    // For every way of rolling 1 to 6 dice
+    for h in 1..7usize.pow(6) {
+        let mut occurances = [0u8; 6];
+        let mut n = h;
+        for slot in occurances.iter_mut() {
+            *slot = (n % 7) as u8;
+            n = n / 7;
+        }
+        if occurances.iter().sum::<u8>() > 6 {
+            continue;
+        }
+        let sorted: Vec<u8> = (1..=6u8).flat_map(|face| std::iter::repeat_n(face, occurances[face as usize - 1] as usize)).collect();
+        // The dice are all the same so the roll can be placed on any of them
+        let mut sample = DiceSetSample::default();
+        for (slot, &face) in sample.sample.iter_mut().zip(sorted.iter()) {
+            *slot = Some(DiceSide::from(face - 1));
+        }
+        let roll = roll_index(&sorted);
+        for (i, p) in domain.scores().enumerate() {
+            let (selection, roll_again) = optimal.query_decision(p, sample.clone());
+            // Mark the first occurance of each face kept so the encoding is the same however the
+            // selection was picked
+            let mut kept = count_sides(&selection.present());
+            let mut byte = if roll_again { ROLL_AGAIN_BIT } else { 0 };
+            for (bit, &face) in sorted.iter().enumerate() {
+                if kept[face as usize - 1] > 0 {
+                    kept[face as usize - 1] -= 1;
+                    byte |= 1 << bit;
+                }
+            }
+            out[HEADER_SIZE + i * ROLL_COUNT + roll] = byte;
+        }
+    }
+    return Ok(out);
+}
+
+/// Reads decisions out of a policy table written by export_policy()
+pub struct PolicyReader<D: Deref<Target = [u8]> = Vec<u8>> {
+    data: D,
+    granularity: u32,
+    cap: u32,
+}
+impl PolicyReader<Vec<u8>> {
+    /// Reads the policy table file at the given path
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        return Self::from_bytes(std::fs::read(path)?);
+    }
+}
+impl<D: Deref<Target = [u8]>> PolicyReader<D> {
+    /// Checks the bytes hold a policy table
+    pub fn from_bytes(data: D) -> io::Result<Self> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+        if data.len() < HEADER_SIZE || &data[..8] != MAGIC {
+            return Err(invalid("Not a policy table"));
+        }
+        let granularity = u32::from_le_bytes(data[8..12].try_into().unwrap());
+        let cap = u32::from_le_bytes(data[12..16].try_into().unwrap());
+        if granularity == 0 || cap % granularity != 0 {
+            return Err(invalid("Policy table has an invalid score domain"));
+        }
+        if data.len() != HEADER_SIZE + (cap / granularity) as usize * ROLL_COUNT {
+            return Err(invalid("Policy table has the wrong length"));
+        }
+        return Ok(Self {data, granularity, cap});
+    }
+
+    pub fn granularity(&self) -> u32 {
+        return self.granularity;
+    }
+
+    /// Score at which the round ends. Every score below it that is a multiple of the granularity is covered.
+    pub fn cap(&self) -> u32 {
+        return self.cap;
+    }
+
+    /// Returns the faces to keep (ascending) and whether to roll again afterwards, given the score so far
+    /// this round and the faces rolled (1 to 6, in any order). Nothing is kept from a bust.
+    ///
+    /// Returns None if the score isn't covered by the table or the faces aren't a valid roll.
+    pub fn decide(&self, score: u32, faces: &[u8]) -> Option<(Vec<u8>, bool)> {
+        if score >= self.cap || score % self.granularity != 0 {
+            return None;
+        }
+        if faces.is_empty() || faces.len() > 6 || faces.iter().any(|face| !(1..=6).contains(face)) {
+            return None;
+        }
+        let mut sorted = faces.to_vec();
+        sorted.sort_unstable();
+        let row = (score / self.granularity) as usize;
+        let byte = self.data[HEADER_SIZE + row * ROLL_COUNT + roll_index(&sorted)];
+        let kept = sorted.iter().enumerate()
+            .filter(|&(bit, _)| byte & (1 << bit) != 0)
+            .map(|(_, &face)| face)
+            .collect();
+        return Some((kept, byte & ROLL_AGAIN_BIT != 0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::farkle::{Dice, FarkleScore, ScoreDomain};
+
+    #[test]
+    fn roll_indices_are_dense() {
+        let mut seen = [false; ROLL_COUNT];
+        for h in 1..7usize.pow(6) {
+            let mut sorted = Vec::new();
+            let mut n = h;
+            for face in 1..=6u8 {
+                sorted.extend(std::iter::repeat_n(face, n % 7));
+                n = n / 7;
+            }
+            if sorted.len() > 6 {
+                continue;
+            }
+            let index = roll_index(&sorted);
+            assert!(!seen[index]);
+            seen[index] = true;
+        }
+        assert!(seen.iter().all(|&b| b));
+    }
+
+    #[test]
+    fn policy_matches_strategy() {
+        let optimal: OptimalStrat = OptimalStrat::with_domain([Dice::default(); 6], ScoreDomain::new(50, 100).unwrap()).iterate();
+        let reader = PolicyReader::from_bytes(export_policy(&optimal).unwrap()).unwrap();
+        assert_eq!((reader.granularity(), reader.cap()), (50, 100));
+        for faces in [vec![1, 5, 5], vec![1, 2, 3, 4, 6, 6], vec![5], vec![2, 3, 4, 6, 6, 3], vec![1, 1, 1, 1, 5]] {
+            let sample = DiceSetSample::from_faces(std::array::from_fn(|i| faces.get(i).copied().unwrap_or(0))).unwrap();
+            for p in [0, 50] {
+                let (selection, roll_again) = optimal.query_decision(FarkleScore::new(p), sample.clone());
+                let mut expected: Vec<u8> = selection.present().iter().map(|side| side.face()).collect();
+                expected.sort_unstable();
+                assert_eq!(reader.decide(p, &faces), Some((expected, roll_again)));
+            }
+        }
+        assert_eq!(reader.decide(100, &[1]), None);
+        assert_eq!(reader.decide(0, &[7]), None);
+
+        let mut dices = [Dice::default(); 6];
+        dices[2] = Dice::new_with_weights([2, 1, 1, 1, 1, 1]);
+        let optimal: OptimalStrat = OptimalStrat::with_domain(dices, ScoreDomain::new(50, 100).unwrap());
+        assert_eq!(export_policy(&optimal), Err(PolicyError::DiceDiffer));
+    }
+}
@@ -0,0 +1,263 @@
+//! Python module (built with the python feature) for working with the solver from Python and numpy.
+//!
+//! Build with maturin, e.g `maturin develop --release`. Samples use the same encoding as the ffi module:
+//! a list of 6 faces, one per die, with 0 when the die is missing. Dice masks are an int where bit i is
+//! set when die i is present.
+
+use crate::farkle::{self, Dice, DiceSetSample, FarkleScore, ScoreDomain};
+use crate::optimal;
+use numpy::ndarray::Array2;
+use numpy::{IntoPyArray, PyArray1, PyArray2};
+use pyo3::exceptions::{PyIOError, PyIndexError, PyValueError};
+use pyo3::prelude::*;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+
+/// Converts an int mask into a boolean mask of dice
+fn decode_mask(mask: u8) -> PyResult<[bool; 6]> {
+    if mask >= 64 {
+        return Err(PyValueError::new_err(format!("Dice mask {} uses more than 6 bits", mask)));
+    }
+    return Ok(std::array::from_fn(|i| mask & (1 << i) != 0));
+}
+
+/// Checks face counts make a hand of at most 6 dice
+fn check_counts(counts: [u8; 6]) -> PyResult<[u8; 6]> {
+    let total: u32 = counts.iter().map(|&c| c as u32).sum();
+    if total > 6 {
+        return Err(PyValueError::new_err(format!("Face counts {:?} add up to {} dice, more than 6", counts, total)));
+    }
+    return Ok(counts);
+}
+
+/// Converts a boolean mask of dice into an int mask
+fn encode_mask(mask: [bool; 6]) -> u8 {
+    return mask.iter().enumerate().map(|(i, &b)| (b as u8) << i).sum();
+}
+
+/// A die with a probability for each side
+#[pyclass(name = "Dice", module = "kcd_farkle_solver", frozen, from_py_object)]
+#[derive(Clone)]
+pub struct PyDice {
+    inner: Dice,
+}
+#[pymethods]
+impl PyDice {
+    /// Creates a die from the probabilities of sides 1 to 6. Raises ValueError if they aren't a distribution.
+    #[new]
+    fn new(probabilities: [f32; 6]) -> PyResult<Self> {
+        let inner = Dice::try_new(probabilities).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        return Ok(Self {inner});
+    }
+
+    /// Creates a die from relative weights of sides 1 to 6
+    #[staticmethod]
+    fn with_weights(weights: [u32; 6]) -> PyResult<Self> {
+        let inner = Dice::try_new_with_weights(weights).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        return Ok(Self {inner});
+    }
+
+    /// A fair die
+    #[staticmethod]
+    fn fair() -> Self {
+        return Self {inner: Dice::default()};
+    }
+
+    #[getter]
+    fn probabilities(&self) -> [f32; 6] {
+        return std::array::from_fn(|i| self.inner[i]);
+    }
+
+    fn expected_roll(&self) -> f32 {
+        return self.inner.expected_roll();
+    }
+
+    fn __repr__(&self) -> String {
+        return format!("Dice({:?})", self.probabilities());
+    }
+}
+
+/// The faces rolled on (up to) 6 die
+#[pyclass(name = "DiceSetSample", module = "kcd_farkle_solver", frozen, eq, skip_from_py_object)]
+#[derive(Clone, PartialEq)]
+pub struct PyDiceSetSample {
+    inner: DiceSetSample,
+}
+#[pymethods]
+impl PyDiceSetSample {
+    /// Creates a sample from 6 faces, one per die, with 0 for a missing die
+    #[new]
+    fn new(faces: [u8; 6]) -> PyResult<Self> {
+        let inner = DiceSetSample::from_faces(faces).ok_or_else(|| PyValueError::new_err(format!("Faces {:?} are not all 0 to 6", faces)))?;
+        return Ok(Self {inner});
+    }
+
+    #[getter]
+    fn faces(&self) -> [u8; 6] {
+        return self.inner.faces();
+    }
+
+    /// Int mask of the dice present
+    #[getter]
+    fn mask(&self) -> u8 {
+        return encode_mask(self.inner.present_mask());
+    }
+
+    /// Number of each face present, from 1 to 6
+    fn counts(&self) -> [u8; 6] {
+        return farkle::count_sides(&self.inner.present());
+    }
+
+    fn __str__(&self) -> String {
+        return self.inner.to_string();
+    }
+
+    fn __repr__(&self) -> String {
+        return format!("DiceSetSample({:?})", self.faces());
+    }
+}
+
+/// Score of a hand given the number of each face from 1 to 6. Returns 0 if not every die is used.
+/// Raises ValueError for more than 6 dice.
+#[pyfunction]
+fn score(counts: [u8; 6]) -> PyResult<u32> {
+    return Ok(farkle::score(check_counts(counts)?).score());
+}
+
+/// Highest score that can be made from some of the given faces, counted from 1 to 6.
+/// Raises ValueError for more than 6 dice.
+#[pyfunction]
+fn best_score(counts: [u8; 6]) -> PyResult<u32> {
+    return Ok(farkle::best_score(check_counts(counts)?).score());
+}
+
+/// Optimal_n strategy solved for a set of 6 die
+#[pyclass(name = "OptimalStrat", module = "kcd_farkle_solver", frozen)]
+pub struct PyOptimalStrat {
+    inner: optimal::OptimalStrat,
+}
+impl PyOptimalStrat {
+    fn check_score(&self, score: u32) -> PyResult<FarkleScore> {
+        let score = FarkleScore::new(score);
+        if self.inner.domain().index(score).is_none() {
+            return Err(PyIndexError::new_err(format!("Score {} lies outside of the score domain", score.score())));
+        }
+        return Ok(score);
+    }
+}
+#[pymethods]
+impl PyOptimalStrat {
+    /// Solves Optimal_n for the given die (fair by default). The GIL is released while solving.
+    #[staticmethod]
+    #[pyo3(signature = (dice=None, iterations=2, granularity=farkle::DEFAULT_GRANULARITY, cap=farkle::DEFAULT_CAP))]
+    fn solve(py: Python<'_>, dice: Option<[PyDice; 6]>, iterations: usize, granularity: u32, cap: u32) -> PyResult<Self> {
+        if iterations == 0 {
+            return Err(PyValueError::new_err("At least 1 iteration is needed"));
+        }
+        let domain = ScoreDomain::new(granularity, cap).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        let dices = dice.map_or([Dice::default(); 6], |dice| dice.map(|d| d.inner));
+        let inner = py.detach(|| optimal::OptimalStrat::solve(dices, domain, iterations, None));
+        return Ok(Self {inner});
+    }
+
+    /// Loads a strategy from a checkpoint JSON file
+    #[staticmethod]
+    fn load(path: &str) -> PyResult<Self> {
+        let file = File::open(path).map_err(|e| PyIOError::new_err(e.to_string()))?;
+        let inner = serde_json::from_reader(BufReader::new(file)).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        return Ok(Self {inner});
+    }
+
+    /// Saves the strategy as a checkpoint JSON file
+    fn save(&self, path: &str) -> PyResult<()> {
+        let file = File::create(path).map_err(|e| PyIOError::new_err(e.to_string()))?;
+        return serde_json::to_writer(BufWriter::new(file), &self.inner).map_err(|e| PyIOError::new_err(e.to_string()));
+    }
+
+    /// Number of rolls looked ahead
+    #[getter]
+    fn n(&self) -> usize {
+        return self.inner.n;
+    }
+
+    #[getter]
+    fn dice(&self) -> [PyDice; 6] {
+        return self.inner.dices.map(|inner| PyDice {inner});
+    }
+
+    /// Expected score gain with the given current score and int mask of dice left
+    fn query_score(&self, score: u32, mask: u8) -> PyResult<f32> {
+        let score = self.check_score(score)?;
+        return Ok(self.inner.query_score(score, decode_mask(mask)?));
+    }
+
+    /// The dice to keep and whether to roll again with the given current score and sample rolled
+    fn query_decision(&self, score: u32, sample: &PyDiceSetSample) -> PyResult<(PyDiceSetSample, bool)> {
+        let score = self.check_score(score)?;
+        let (inner, roll_again) = self.inner.query_decision(score, sample.inner.clone());
+        return Ok((PyDiceSetSample {inner}, roll_again));
+    }
+
+    /// Every score tracked, matching the rows of expected_scores()
+    fn scores<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<u32>> {
+        let scores: Vec<u32> = self.inner.domain().scores().map(|p| p.score()).collect();
+        return scores.into_pyarray(py);
+    }
+
+    /// Expected score gains as a (scores, 64) array. Row i is the score scores()[i] and column j the int
+    /// mask of dice left.
+    fn expected_scores<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<f32>> {
+        let domain = self.inner.domain();
+        let table = Array2::from_shape_fn((domain.len(), 64), |(i, mask)| {
+            return self.inner.query_score(domain.score(i), std::array::from_fn(|d| mask & (1 << d) != 0));
+        });
+        return table.into_pyarray(py);
+    }
+
+    /// Busting probabilities as an array of 64, indexed by the int mask of dice rolled
+    fn bust_prob<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f32>> {
+        let mut probs = vec![0.0; 64];
+        for (mask, &p) in self.inner.bust_prob.iter() {
+            probs[encode_mask(mask) as usize] = p;
+        }
+        return probs.into_pyarray(py);
+    }
+
+    fn __repr__(&self) -> String {
+        let domain = self.inner.domain();
+        return format!("OptimalStrat(n={}, granularity={}, cap={})", self.inner.n, domain.granularity(), domain.cap());
+    }
+}
+
+#[pymodule]
+fn kcd_farkle_solver(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyDice>()?;
+    m.add_class::<PyDiceSetSample>()?;
+    m.add_class::<PyOptimalStrat>()?;
+    m.add_function(wrap_pyfunction!(score, m)?)?;
+    m.add_function(wrap_pyfunction!(best_score, m)?)?;
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks() {
+        assert_eq!(decode_mask(0b100001).unwrap(), [true, false, false, false, false, true]);
+        assert_eq!(encode_mask([true, false, false, false, false, true]), 0b100001);
+        assert!(decode_mask(64).is_err());
+    }
+
+    #[test]
+    fn counts() {
+        assert_eq!(score([1, 0, 0, 0, 1, 0]).unwrap(), 150);
+        assert_eq!(best_score([0, 0, 0, 0, 0, 6]).unwrap(), 4800);
+        assert_eq!(best_score([0; 6]).unwrap(), 0);
+        // More than 6 dice, including counts that would overflow a u8 total
+        assert!(score([7, 0, 0, 0, 0, 0]).is_err());
+        assert!(best_score([1, 1, 1, 1, 1, 2]).is_err());
+        assert!(best_score([255, 255, 0, 0, 0, 0]).is_err());
+    }
+}
@@ -0,0 +1,148 @@
+//! How much the expected score of a round depends on each face weight of each die. Useful for
+//! judging which special dice are worth acquiring.
+//!
+//! The objective is the expected round score actually achieved by following the solved strategy's
+//! decisions, as computed by strategy::expected_round_score(). This is not the strategy's own estimate
+//! (OptimalStrat::query_score()), which in general differs. They agree for Optimal_1 as it banks every
+//! roll.
+//!
+//! Sensitivities are finite differences of the objective while holding the decisions fixed, as
+//! re-solving for every shifted die would be far too slow. Changing decisions only matters to second
+//! order near an optimum (the envelope theorem), so this approximates how the value of re-solved play
+//! changes.
+
+use crate::farkle::{Dice, DiceError, DiceSide};
+use crate::optimal::OptimalStrat;
+use crate::strategy::expected_round_score;
+use std::fmt;
+
+/// Sensitivity of the expected round score to every probability of every die
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiceSensitivity {
+    /// Expected round score achieved by following the strategy with the unchanged dice. See the module
+    /// documentation for how this relates to OptimalStrat::query_score().
+    pub base: f32,
+    /// derivatives[die][side] is the change in expected round score per unit of probability moved onto that
+    /// side of that die. Probability is taken from the die's other sides in proportion to their size.
+    pub derivatives: [[f32; 6]; 6],
+}
+impl DiceSensitivity {
+    /// Computes the sensitivities of the given solved strategy using central differences of the given step size
+    /// (above 0 and at most 1).
+    ///
+    /// Requires 72 evaluations of the strategy so takes a while.
+    pub fn new(optimal: &OptimalStrat, step: f32) -> Self {
+        assert!(step > 0.0 && step <= 1.0, "Step size must be above 0 and at most 1");
+        let domain = optimal.domain();
+        let round_score = |dices: &[Dice; 6]| expected_round_score(optimal, dices, domain);
+        let base = round_score(&optimal.dices);
+        let mut derivatives = [[0.0; 6]; 6];
+        for (die, row) in derivatives.iter_mut().enumerate() {
+            for (side, slot) in row.iter_mut().enumerate() {
+                let side = DiceSide::from(side as u8);
+                let p = optimal.dices[die][side];
+                // Fall back to a one sided difference at the edges. Clamped as the probabilities may
+                // be a rounding error outside of 0 to 1.
+                let up = step.min(1.0 - p).max(0.0);
+                let down = step.min(p).max(0.0);
+                let mut dices = optimal.dices;
+                dices[die] = shift(&optimal.dices[die], side, up).expect("Shifted dice are valid");
+                let higher = round_score(&dices);
+                dices[die] = shift(&optimal.dices[die], side, -down).expect("Shifted dice are valid");
+                let lower = round_score(&dices);
+                *slot = (higher - lower) / (up + down);
+            }
+        }
+        return Self {base, derivatives};
+    }
+
+    /// Every (die, side, derivative) ordered from the most to least valuable probability to gain
+    pub fn ranked(&self) -> Vec<(usize, DiceSide, f32)> {
+        let mut out: Vec<_> = self.derivatives.iter().enumerate()
+            .flat_map(|(die, row)| row.iter().enumerate().map(move |(side, &d)| (die, DiceSide::from(side as u8), d)))
+            .collect();
+        out.sort_by(|a, b| b.2.total_cmp(&a.2));
+        return out;
+    }
+}
+impl fmt::Display for DiceSensitivity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Expected round score: {:.3}", self.base)?;
+        writeln!(f, "Die | side 1   side 2   side 3   side 4   side 5   side 6")?;
+        for (die, row) in self.derivatives.iter().enumerate() {
+            write!(f, "{:>3} |", die + 1)?;
+            for d in row.iter() {
+                write!(f, " {:>8.2}", d)?;
+            }
+            writeln!(f)?;
+        }
+        return Ok(());
+    }
+}
+
+/// Moves delta of probability onto the given side, taking it from the other sides in proportion to their size
+fn shift(dice: &Dice, side: DiceSide, delta: f32) -> Result<Dice, DiceError> {
+    let p = dice[side];
+    let rest = 1.0 - p;
+    let mut probabilities = [0.0; 6];
+    for (i, slot) in probabilities.iter_mut().enumerate() {
+        if i == side as usize {
+            *slot = p + delta;
+        } else if rest > 0.0 {
+            *slot = dice[i] * (rest - delta) / rest;
+        } else {
+            // Every other side is impossible, so spread the change out evenly
+            *slot = -delta / 5.0;
+        }
+    }
+    // Clean up rounding below 0
+    return Dice::try_normalised(probabilities.map(|p: f32| p.max(0.0)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::farkle::FarkleScore;
+
+    #[test]
+    fn shift_keeps_proportions() {
+        let dice = Dice::new_with_weights([2, 1, 1, 1, 1, 2]);
+        let shifted = shift(&dice, DiceSide::Two, 0.1).unwrap();
+        assert!((shifted[DiceSide::Two] - 0.225).abs() < 1e-6);
+        // Other sides keep their 2:1 ratios
+        assert!((shifted[DiceSide::One] / shifted[DiceSide::Three] - 2.0).abs() < 1e-5);
+        assert!(((0..6).map(|i| shifted[i]).sum::<f32>() - 1.0).abs() < 1e-6);
+
+        let certain = Dice::new([1.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+        let shifted = shift(&certain, DiceSide::One, -0.5).unwrap();
+        assert!((shifted[DiceSide::Six] - 0.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn sensitivities() {
+        use crate::farkle::ScoreDomain;
+
+        // Sides that are certain or impossible only have a one sided difference
+        let mut dices = [Dice::default(); 6];
+        dices[0] = Dice::new([1.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+        let optimal: OptimalStrat = OptimalStrat::with_domain(dices, ScoreDomain::new(50, 100).unwrap());
+        let sensitivity = DiceSensitivity::new(&optimal, 0.05);
+        assert!(sensitivity.derivatives.iter().flatten().all(|d| d.is_finite()));
+        // The objective is the score achieved by following the strategy, which Optimal_1 estimates exactly
+        assert_eq!(sensitivity.base, expected_round_score(&optimal, &dices, optimal.domain()));
+        assert!((sensitivity.base - optimal.query_score(FarkleScore::new(0), [true; 6])).abs() < 1e-3);
+        // Ones score so are worth more than twos
+        assert!(sensitivity.derivatives[1][0] > sensitivity.derivatives[1][1]);
+        assert!(sensitivity.derivatives[0][1] < 0.0);
+        let ranked = sensitivity.ranked();
+        assert_eq!(ranked.len(), 36);
+        assert!(ranked.windows(2).all(|w| w[0].2 >= w[1].2));
+    }
+
+    #[test]
+    #[should_panic(expected = "Step size must be above 0 and at most 1")]
+    fn zero_step() {
+        let optimal: OptimalStrat = OptimalStrat::with_domain([Dice::default(); 6], crate::farkle::ScoreDomain::new(50, 100).unwrap());
+        DiceSensitivity::new(&optimal, 0.0);
+    }
+}
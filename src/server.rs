@@ -0,0 +1,151 @@
+//! HTTP JSON API (built with the server feature) serving queries against a pre-solved strategy, so
+//! tooling in other languages can use the solver. The server binary listens for requests and passes
+//! them on to handle().
+//!
+//! Every endpoint takes a POST with a JSON body:
+//!  - `/score`: `{"score": 300, "dice": [true, true, false, false, false, false]}` gives the expected score gain
+//!  - `/decision`: an agent::RemoteRequest gives an agent::RemoteResponse
+//!  - `/explain`: an agent::RemoteRequest gives the optimal::DecisionExplanation of every option
+//!  - `/bust_prob`: `{"dice": [true, ...]}` gives the probability of those dice going bust
+//!  - `/breakdown`: a DiceSetSample gives the farkle::ScoreBreakdown of the dice present
+
+use crate::agent::{RemoteRequest, RemoteResponse};
+use crate::farkle::{count_sides, score_breakdown, DiceSetSample, FarkleScore};
+use crate::optimal::MappedStrat;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::ops::Deref;
+use tiny_http::Method;
+
+#[derive(Deserialize)]
+struct ScoreQuery {
+    score: FarkleScore,
+    dice: [bool; 6],
+}
+
+#[derive(Serialize)]
+struct ScoreAnswer {
+    expected_score: f32,
+}
+
+#[derive(Deserialize)]
+struct BustQuery {
+    dice: [bool; 6],
+}
+
+#[derive(Serialize)]
+struct BustAnswer {
+    bust_prob: f32,
+}
+
+#[derive(Serialize)]
+struct ErrorAnswer {
+    error: String,
+}
+
+/// Parses the body of a request
+fn parse<T: DeserializeOwned>(body: &str) -> Result<T, (u16, String)> {
+    return serde_json::from_str(body).map_err(|e| (400, format!("Invalid request body: {}", e)));
+}
+
+/// Checks the score is one the strategy keeps track of
+fn check_score<D: Deref<Target = [u8]>>(optimal: &MappedStrat<D>, score: FarkleScore) -> Result<(), (u16, String)> {
+    if optimal.domain().index(score).is_none() {
+        return Err((400, format!("Score {} lies outside of the solved score domain", score.score())));
+    }
+    return Ok(());
+}
+
+/// Answers a single request, giving the JSON to send back or an error status and message
+pub fn handle<D: Deref<Target = [u8]>>(optimal: &MappedStrat<D>, method: &Method, path: &str, body: &str) -> Result<String, (u16, String)> {
+    if *method != Method::Post {
+        return Err((405, "Only POST is supported".to_string()));
+    }
+    let answer = match path {
+        "/score" => {
+            let query: ScoreQuery = parse(body)?;
+            check_score(optimal, query.score)?;
+            serde_json::to_string(&ScoreAnswer {expected_score: optimal.query_score(query.score, query.dice)})
+        }
+        "/decision" => {
+            let request: RemoteRequest = parse(body)?;
+            check_score(optimal, request.state.score)?;
+            let (selection, roll_again) = optimal.query_decision(request.state.score, request.sample);
+            serde_json::to_string(&RemoteResponse {selection, roll_again})
+        }
+        "/explain" => {
+            let request: RemoteRequest = parse(body)?;
+            check_score(optimal, request.state.score)?;
+            serde_json::to_string(&optimal.explain_decision(request.state.score, &request.sample))
+        }
+        "/bust_prob" => {
+            let query: BustQuery = parse(body)?;
+            serde_json::to_string(&BustAnswer {bust_prob: optimal.bust_prob.get(&query.dice).expect("Masks are always in range")})
+        }
+        "/breakdown" => {
+            let sample: DiceSetSample = parse(body)?;
+            serde_json::to_string(&score_breakdown(count_sides(&sample.present())))
+        }
+        _ => return Err((404, format!("No endpoint at {}", path))),
+    };
+    return answer.map_err(|e| (500, e.to_string()));
+}
+
+/// Turns the result of handle() into the status and JSON body of the response. Errors are sent as
+/// `{"error": <message>}`.
+pub fn response_body(result: Result<String, (u16, String)>) -> (u16, String) {
+    match result {
+        Ok(answer) => return (200, answer),
+        Err((status, error)) => return (status, serde_json::to_string(&ErrorAnswer {error}).expect("Strings always serialise")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::farkle::{Dice, ScoreDomain};
+    use crate::optimal::OptimalStrat;
+    use crate::strategy::TurnState;
+
+    #[test]
+    fn routing() {
+        let dir = std::env::temp_dir().join(format!("kcd_server_{}", std::process::id()));
+        let solved: OptimalStrat = OptimalStrat::with_domain([Dice::default(); 6], ScoreDomain::new(50, 100).unwrap()).iterate();
+        solved.save_tables(&dir).unwrap();
+        let optimal = MappedStrat::open(&dir).unwrap();
+        let post = |path: &str, body: &str| handle(&optimal, &Method::Post, path, body);
+
+        let answer = post("/score", r#"{"score": 50, "dice": [true, true, false, false, false, false]}"#).unwrap();
+        let expected = solved.query_score(FarkleScore::new(50), [true, true, false, false, false, false]);
+        assert_eq!(answer, serde_json::to_string(&ScoreAnswer {expected_score: expected}).unwrap());
+        let answer = post("/bust_prob", r#"{"dice": [true, false, false, false, false, false]}"#).unwrap();
+        assert_eq!(answer, serde_json::to_string(&BustAnswer {bust_prob: solved.bust_prob[[true, false, false, false, false, false]]}).unwrap());
+
+        let sample = DiceSetSample::from_faces([1, 2, 3, 4, 6, 6]).unwrap();
+        let request = RemoteRequest {state: TurnState::new(FarkleScore::new(0), [true; 6]), sample: sample.clone()};
+        let request = serde_json::to_string(&request).unwrap();
+        let response: RemoteResponse = serde_json::from_str(&post("/decision", &request).unwrap()).unwrap();
+        assert_eq!((response.selection, response.roll_again), solved.query_decision(FarkleScore::new(0), sample.clone()));
+        let answer = post("/explain", &request).unwrap();
+        assert_eq!(answer, serde_json::to_string(&solved.explain_decision(FarkleScore::new(0), &sample)).unwrap());
+        let answer = post("/breakdown", &serde_json::to_string(&sample).unwrap()).unwrap();
+        assert_eq!(answer, serde_json::to_string(&score_breakdown(count_sides(&sample.present()))).unwrap());
+
+        // Bad requests
+        assert_eq!(post("/score", r#"{"score": 100, "dice": [true, true, true, true, true, true]}"#).unwrap_err().0, 400);
+        assert_eq!(post("/score", r#"{"score": 30, "dice": [true, true, true, true, true, true]}"#).unwrap_err().0, 400);
+        assert_eq!(post("/score", "not json").unwrap_err().0, 400);
+        let request = RemoteRequest {state: TurnState::new(FarkleScore::new(500), [true; 6]), sample: sample.clone()};
+        let request = serde_json::to_string(&request).unwrap();
+        assert_eq!(post("/decision", &request).unwrap_err(), (400, "Score 500 lies outside of the solved score domain".to_string()));
+        assert_eq!(post("/explain", &request).unwrap_err().0, 400);
+        assert_eq!(post("/bust_prob", r#"{"dice": [true]}"#).unwrap_err().0, 400);
+        assert_eq!(post("/breakdown", r#"{"sample": [7]}"#).unwrap_err().0, 400);
+        assert_eq!(post("/missing", "{}").unwrap_err().0, 404);
+        assert_eq!(handle(&optimal, &Method::Get, "/score", "").unwrap_err().0, 405);
+
+        assert_eq!(response_body(Ok("{}".to_string())), (200, "{}".to_string()));
+        assert_eq!(response_body(post("/missing", "{}")), (404, r#"{"error":"No endpoint at /missing"}"#.to_string()));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,191 @@
+//! Common interface for anything that makes decisions within a round of Farkle, along with an
+//! exact evaluator of the expected score a strategy achieves
+
+use crate::hash::{PerfectHash, PerfectHashing, ScoreMap};
+use crate::farkle::{best_selection, count_sides, not_busted, score, Dice, DiceSet, DiceSetSample, FarkleScore, ScoreDomain};
+use crate::numeric::Numeric;
+use crate::optimal::{MappedStrat, OptimalStrat};
+use crate::parallel::map_indexed;
+use serde::{Deserialize, Serialize};
+use std::ops::Deref;
+
+/// Default score needed to win a game of Farkle
+pub const DEFAULT_TARGET: u32 = 6000;
+
+/// Everything known to a player at the point of making a decision
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TurnState {
+    /// The current score for this round
+    pub score: FarkleScore,
+    /// Boolean mask of the dice that were rolled
+    pub dice: [bool; 6],
+    /// Score the player has banked from previous rounds
+    pub banked: u32,
+    /// Score the opponent has banked from previous rounds
+    pub opponent_banked: u32,
+    /// Score needed to win the game
+    pub target: u32,
+}
+impl TurnState {
+    /// Creates the state for a round played on its own. I.e nothing has been banked by either player yet.
+    pub fn new(score: FarkleScore, dice: [bool; 6]) -> Self {
+        return Self {score, dice, banked: 0, opponent_banked: 0, target: DEFAULT_TARGET};
+    }
+}
+
+/// A decision making policy for Farkle. Implemented by the solvers, heuristics and anything
+/// else that can play (humans, remote agents...) so they can be swapped for each other freely.
+pub trait Strategy {
+    /// Returns the decision made with the given turn state and the dice sample that was just rolled.
+    ///
+    /// The DiceSetSample returned shows what dice have been selected.
+    ///
+    /// The boolean returned indicates whether to roll again.
+    ///  - 0 = End turn here
+    ///  - 1 = Roll again
+    fn decide(&self, state: &TurnState, sample: &DiceSetSample) -> (DiceSetSample, bool);
+}
+
+impl<S: Strategy + ?Sized> Strategy for &S {
+    fn decide(&self, state: &TurnState, sample: &DiceSetSample) -> (DiceSetSample, bool) {
+        return (**self).decide(state, sample);
+    }
+}
+
+impl<S: Strategy + ?Sized> Strategy for Box<S> {
+    fn decide(&self, state: &TurnState, sample: &DiceSetSample) -> (DiceSetSample, bool) {
+        return (**self).decide(state, sample);
+    }
+}
+
+/// Returns the best hand of the sample if banking it reaches the target score, winning the game
+fn winning_bank(state: &TurnState, sample: &DiceSetSample) -> Option<DiceSetSample> {
+    let selection = best_selection(sample.clone());
+    if state.banked + state.score.score() + score(count_sides(&selection.present())).score() < state.target {
+        return None;
+    }
+    return Some(selection);
+}
+
+/// Plays to maximise the expected round score, except for banking whenever that wins the game. The
+/// opponent's score isn't taken into account.
+impl<T: Numeric> Strategy for OptimalStrat<T> {
+    fn decide(&self, state: &TurnState, sample: &DiceSetSample) -> (DiceSetSample, bool) {
+        if let Some(selection) = winning_bank(state, sample) {
+            return (selection, false);
+        }
+        return self.query_decision(state.score, sample.clone());
+    }
+}
+
+/// Same as the Strategy implementation of OptimalStrat
+impl<D: Deref<Target = [u8]>> Strategy for MappedStrat<D> {
+    fn decide(&self, state: &TurnState, sample: &DiceSetSample) -> (DiceSetSample, bool) {
+        if let Some(selection) = winning_bank(state, sample) {
+            return (selection, false);
+        }
+        return self.query_decision(state.score, sample.clone());
+    }
+}
+
+/// Computes the expected score gain of following the given strategy for all possible scores and
+/// die subsets. The output is directly comparable to OptimalStrat::expected_scores.
+///
+/// Some notes on how decisions are treated:
+///  - Selections that do not form a valid hand are treated as going bust
+///  - Reaching the cap of the domain (or more) ends the round as the game would have been won
+///  - Strategies are given the cap of the domain as the target score, with nothing banked by either player
+pub fn evaluate<S: Strategy + Sync + ?Sized>(strategy: &S, dices: &[Dice; 6], domain: ScoreDomain) -> ScoreMap<[bool; 6], f32> {
+    let mut expected_scores = ScoreMap::<[bool; 6], f32>::new(domain);
+    let max_score = domain.max_score().score();
+    // Rolling again always increases the score. So working down from the highest score means
+    // every follow up state has already been evaluated by the time it is needed.
+    let masks: Vec<[bool; 6]> = (1..<[bool; 6]>::SET_SIZE)
+        .map(|h| <[bool; 6]>::from_perfhash(PerfectHash::new(h)))
+        .collect();
+    for p in domain.scores().rev() {
+        let layer = map_indexed(&masks, false, |_, &mask| {
+            let diceset = DiceSet::new(dices, mask);
+            let mut expected_score_gain = 0.0;
+            for (sample, prob) in diceset.iter_outcomes() {
+                let occurances = count_sides(&sample.present());
+                // Bust, nothing gained and the current score is lost
+                if !not_busted(&occurances) {
+                    expected_score_gain -= prob * p.score() as f32;
+                    continue;
+                }
+                let state = TurnState {target: domain.cap(), ..TurnState::new(p, mask)};
+                let (selection, roll_again) = strategy.decide(&state, &sample);
+                let select_score = score(count_sides(&selection.present())).score();
+                // Invalid hands can't be banked so treat them as a bust
+                if select_score == 0 {
+                    expected_score_gain -= prob * p.score() as f32;
+                    continue;
+                }
+                let next_score = p.score() + select_score;
+                let mut gain = select_score as f32;
+                if roll_again && next_score <= max_score {
+                    let unselected_dice = diceset.new_subset(&selection.present_mask().map(|b| !b));
+                    gain += expected_scores[(FarkleScore::new(next_score), unselected_dice.select_mask)];
+                }
+                expected_score_gain += prob * gain;
+            }
+            return (mask, expected_score_gain);
+        });
+        for (mask, expected_score_gain) in layer {
+            expected_scores[(p, mask)] = expected_score_gain;
+        }
+        // Having no dice means the player loops back round to 6-dice
+        expected_scores[(p, [false; 6])] = expected_scores[(p, [true; 6])];
+    }
+    return expected_scores;
+}
+
+/// Computes the expected final score of a round (starting from 0 with all 6 die) when following the given strategy
+pub fn expected_round_score<S: Strategy + Sync + ?Sized>(strategy: &S, dices: &[Dice; 6], domain: ScoreDomain) -> f32 {
+    return evaluate(strategy, dices, domain)[(FarkleScore::new(0), [true; 6])];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heuristic::{KeepRule, ThresholdStrat};
+
+    #[test]
+    fn evaluate_always_bank() {
+        // Banking straight away gains exactly what Optimal_1 expects
+        let dices = [Dice::default(); 6];
+        let domain = ScoreDomain::new(50, 200).unwrap();
+        let expected_scores = evaluate(&ThresholdStrat::uniform(0, KeepRule::Max), &dices, domain);
+        let optimal: OptimalStrat = OptimalStrat::with_domain(dices, domain);
+        for ((p, mask), &v) in expected_scores.iter() {
+            assert!((v - optimal.query_score(p, mask)).abs() < 1e-2, "{} with {:?}: {} != {}", p.score(), mask, v, optimal.query_score(p, mask));
+        }
+    }
+
+    #[test]
+    fn heuristics_round_scores() {
+        let dices = [Dice::default(); 6];
+        let domain = ScoreDomain::new(50, 200).unwrap();
+        let always_bank = evaluate(&ThresholdStrat::uniform(0, KeepRule::Max), &dices, domain);
+        let bank_at_300 = expected_round_score(&ThresholdStrat::uniform(300, KeepRule::Max), &dices, domain);
+        assert_eq!(expected_round_score(&ThresholdStrat::uniform(0, KeepRule::Max), &dices, domain), always_bank[(FarkleScore::new(0), [true; 6])]);
+        // Rolling again with few points on the table pays off
+        assert!(bank_at_300 > always_bank[(FarkleScore::new(0), [true; 6])]);
+        // Keeping the fewest dice scores less when banking straight away
+        let minimum = expected_round_score(&ThresholdStrat::uniform(0, KeepRule::Minimum), &dices, domain);
+        assert!(minimum < always_bank[(FarkleScore::new(0), [true; 6])]);
+    }
+
+    #[test]
+    fn optimal_banks_to_win() {
+        let optimal: OptimalStrat = OptimalStrat::with_domain([Dice::default(); 6], ScoreDomain::new(50, 200).unwrap()).iterate();
+        let sample = DiceSetSample::from_faces([1, 2, 3, 4, 6, 6]).unwrap();
+        let state = TurnState::new(FarkleScore::new(0), [true; 6]);
+        assert_eq!(optimal.decide(&state, &sample), optimal.query_decision(state.score, sample.clone()));
+        assert!(optimal.decide(&state, &sample).1);
+        // Keeping the 1 reaches the target
+        let state = TurnState {banked: 5900, ..state};
+        assert_eq!(optimal.decide(&state, &sample), (best_selection(sample.clone()), false));
+    }
+}
@@ -0,0 +1,353 @@
+//! Persisting a PerfectHashMap as a flat little-endian array so it can be opened (memory mapped)
+//! without any parsing
+//!
+//! File layout:
+//!  - 8 byte magic ("KCDFPHM" followed by a format version byte)
+//!  - Set size of the key as a u64
+//!  - Size in bytes of a single value as a u64
+//!  - Every value in order of its key's hash
+//!
+//! ScoreMaps use the same layout, with the set size covering every score of their domain.
+
+use crate::farkle::{DiceSetSample, FarkleScore, ScoreDomain};
+use crate::hash::{Backend, PerfectHash, PerfectHashMap, PerfectHashing, ScoreMap};
+#[cfg(not(target_arch = "wasm32"))]
+use memmap2::Mmap;
+use std::fs::File;
+use std::io::{self, Write};
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::path::Path;
+
+const MAGIC: &[u8; 8] = b"KCDFPHM\x01";
+const HEADER_SIZE: usize = 24;
+
+/// Plain old data that can be stored with a fixed size little-endian encoding
+pub trait FlatValue: Sized {
+    /// Size of the encoding in bytes
+    const SIZE: usize;
+
+    /// Writes the encoding into out, which is exactly SIZE bytes long
+    fn write_le(&self, out: &mut [u8]);
+    /// Reads the encoding from bytes, which is exactly SIZE bytes long
+    fn read_le(bytes: &[u8]) -> Self;
+}
+
+impl FlatValue for f32 {
+    const SIZE: usize = 4;
+
+    fn write_le(&self, out: &mut [u8]) {
+        out.copy_from_slice(&self.to_le_bytes());
+    }
+    fn read_le(bytes: &[u8]) -> Self {
+        return Self::from_le_bytes(bytes.try_into().unwrap());
+    }
+}
+
+impl FlatValue for f64 {
+    const SIZE: usize = 8;
+
+    fn write_le(&self, out: &mut [u8]) {
+        out.copy_from_slice(&self.to_le_bytes());
+    }
+    fn read_le(bytes: &[u8]) -> Self {
+        return Self::from_le_bytes(bytes.try_into().unwrap());
+    }
+}
+
+impl FlatValue for u32 {
+    const SIZE: usize = 4;
+
+    fn write_le(&self, out: &mut [u8]) {
+        out.copy_from_slice(&self.to_le_bytes());
+    }
+    fn read_le(bytes: &[u8]) -> Self {
+        return Self::from_le_bytes(bytes.try_into().unwrap());
+    }
+}
+
+impl FlatValue for bool {
+    const SIZE: usize = 1;
+
+    fn write_le(&self, out: &mut [u8]) {
+        out[0] = *self as u8;
+    }
+    fn read_le(bytes: &[u8]) -> Self {
+        return bytes[0] != 0;
+    }
+}
+
+/// Stored as its perfect hash
+impl FlatValue for DiceSetSample {
+    const SIZE: usize = 4;
+
+    fn write_le(&self, out: &mut [u8]) {
+        (usize::from(self.to_perfhash()) as u32).write_le(out);
+    }
+    fn read_le(bytes: &[u8]) -> Self {
+        return Self::from_perfhash(PerfectHash::new(u32::read_le(bytes) as usize));
+    }
+}
+
+impl<A: FlatValue, B: FlatValue> FlatValue for (A, B) {
+    const SIZE: usize = A::SIZE + B::SIZE;
+
+    fn write_le(&self, out: &mut [u8]) {
+        self.0.write_le(&mut out[..A::SIZE]);
+        self.1.write_le(&mut out[A::SIZE..]);
+    }
+    fn read_le(bytes: &[u8]) -> Self {
+        return (A::read_le(&bytes[..A::SIZE]), B::read_le(&bytes[A::SIZE..]));
+    }
+}
+
+/// Writes the header of a flat table
+fn write_header<W: Write>(writer: &mut W, set_size: usize, value_size: usize) -> io::Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_all(&(set_size as u64).to_le_bytes())?;
+    writer.write_all(&(value_size as u64).to_le_bytes())?;
+    return Ok(());
+}
+
+/// Checks the bytes hold a flat table with the given set and value sizes
+fn check_header(data: &[u8], set_size: usize, value_size: usize) -> io::Result<()> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+    if data.len() < HEADER_SIZE || &data[..8] != MAGIC {
+        return Err(invalid("Not a flat table"));
+    }
+    let stored_set_size = u64::from_le_bytes(data[8..16].try_into().unwrap());
+    let stored_value_size = u64::from_le_bytes(data[16..24].try_into().unwrap());
+    if stored_set_size != set_size as u64 || stored_value_size != value_size as u64 {
+        return Err(invalid("Flat table was written with different key or value types"));
+    }
+    if data.len() != HEADER_SIZE + set_size * value_size {
+        return Err(invalid("Flat table has the wrong length"));
+    }
+    return Ok(());
+}
+
+/// Bytes of a table opened from a file. Memory mapped where supported, otherwise read into memory.
+#[cfg(not(target_arch = "wasm32"))]
+pub type TableBytes = Mmap;
+/// Bytes of a table opened from a file. Memory mapped where supported, otherwise read into memory.
+#[cfg(target_arch = "wasm32")]
+pub type TableBytes = Vec<u8>;
+
+/// Memory maps a file
+#[cfg(not(target_arch = "wasm32"))]
+fn map_file(path: impl AsRef<Path>) -> io::Result<TableBytes> {
+    let file = File::open(path)?;
+    // SAFETY: Tables are only ever written whole by save_flat() and opened read-only. Modifying a
+    // table file while it is mapped is not supported.
+    return unsafe { Mmap::map(&file) };
+}
+
+/// Reads a file, as there is no memory mapping on wasm
+#[cfg(target_arch = "wasm32")]
+fn map_file(path: impl AsRef<Path>) -> io::Result<TableBytes> {
+    return std::fs::read(path);
+}
+
+impl<K: PerfectHashing, V: Sized + Default + FlatValue, B: Backend<V>> PerfectHashMap<K, V, B> {
+    /// Writes out every value (including unwritten ones of a sparse map) as a flat table
+    pub fn write_flat<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_header(writer, K::SET_SIZE, V::SIZE)?;
+        let mut buf = vec![0u8; V::SIZE];
+        for (_, value) in self.iter() {
+            value.write_le(&mut buf);
+            writer.write_all(&buf)?;
+        }
+        return Ok(());
+    }
+
+    /// Writes out the map as a flat table file
+    pub fn save_flat(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = io::BufWriter::new(File::create(path)?);
+        self.write_flat(&mut writer)?;
+        return writer.flush();
+    }
+}
+
+/// Read-only view of a flat table written by PerfectHashMap::write_flat(). Values are decoded on
+/// access so the underlying bytes can come straight from a memory mapped file.
+pub struct FlatPerfectHashMap<K: PerfectHashing, V: FlatValue, D: Deref<Target = [u8]> = TableBytes> {
+    data: D,
+    associated: PhantomData<(K, V)>,
+}
+impl<K: PerfectHashing, V: FlatValue, D: Deref<Target = [u8]>> FlatPerfectHashMap<K, V, D> {
+    /// Wraps the bytes of a flat table, checking they match the key and value types
+    pub fn from_bytes(data: D) -> io::Result<Self> {
+        check_header(&data, K::SET_SIZE, V::SIZE)?;
+        return Ok(Self {data, associated: PhantomData});
+    }
+
+    /// Returns the value for the key. None if the key lies outside of the set being hashed.
+    pub fn get(&self, key: &K) -> Option<V> {
+        let start = HEADER_SIZE + usize::from(key.try_to_perfhash()?) * V::SIZE;
+        return Some(V::read_le(&self.data[start..start + V::SIZE]));
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (K, V)> + '_ {
+        return (0..K::SET_SIZE).map(|h| {
+            let start = HEADER_SIZE + h * V::SIZE;
+            return (K::from_perfhash(PerfectHash::new(h)), V::read_le(&self.data[start..start + V::SIZE]));
+        });
+    }
+}
+impl<K: PerfectHashing, V: FlatValue> FlatPerfectHashMap<K, V, TableBytes> {
+    /// Memory maps a flat table file (see TableBytes)
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        return Self::from_bytes(map_file(path)?);
+    }
+}
+
+impl<K: PerfectHashing, V: Sized + Default + FlatValue> ScoreMap<K, V> {
+    /// Writes out every value as a flat table. The domain isn't stored so must be saved separately.
+    pub fn write_flat<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_header(writer, self.set_size(), V::SIZE)?;
+        let mut buf = vec![0u8; V::SIZE];
+        for (_, value) in self.iter() {
+            value.write_le(&mut buf);
+            writer.write_all(&buf)?;
+        }
+        return Ok(());
+    }
+
+    /// Writes out the map as a flat table file
+    pub fn save_flat(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = io::BufWriter::new(File::create(path)?);
+        self.write_flat(&mut writer)?;
+        return writer.flush();
+    }
+}
+
+/// Read-only view of a flat table written by ScoreMap::write_flat(). Same as FlatPerfectHashMap
+/// but the scores covered are given by a ScoreDomain.
+pub struct FlatScoreMap<K: PerfectHashing, V: FlatValue, D: Deref<Target = [u8]> = TableBytes> {
+    data: D,
+    domain: ScoreDomain,
+    associated: PhantomData<(K, V)>,
+}
+impl<K: PerfectHashing, V: FlatValue, D: Deref<Target = [u8]>> FlatScoreMap<K, V, D> {
+    /// Wraps the bytes of a flat table, checking they match the domain, key and value types
+    pub fn from_bytes(data: D, domain: ScoreDomain) -> io::Result<Self> {
+        check_header(&data, domain.len() * K::SET_SIZE, V::SIZE)?;
+        return Ok(Self {data, domain, associated: PhantomData});
+    }
+
+    /// The range of scores this map covers
+    pub fn domain(&self) -> ScoreDomain {
+        return self.domain;
+    }
+
+    /// Returns the value for the key. None if the key lies outside of the set being hashed.
+    pub fn get(&self, key: &(FarkleScore, K)) -> Option<V> {
+        let hash = self.domain.index(key.0)? * K::SET_SIZE + usize::from(key.1.try_to_perfhash()?);
+        let start = HEADER_SIZE + hash * V::SIZE;
+        return Some(V::read_le(&self.data[start..start + V::SIZE]));
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = ((FarkleScore, K), V)> + '_ {
+        return (0..self.domain.len() * K::SET_SIZE).map(|h| {
+            let start = HEADER_SIZE + h * V::SIZE;
+            let key = (self.domain.score(h / K::SET_SIZE), K::from_perfhash(PerfectHash::new(h % K::SET_SIZE)));
+            return (key, V::read_le(&self.data[start..start + V::SIZE]));
+        });
+    }
+}
+impl<K: PerfectHashing, V: FlatValue> FlatScoreMap<K, V, TableBytes> {
+    /// Memory maps a flat table file (see TableBytes)
+    pub fn open(path: impl AsRef<Path>, domain: ScoreDomain) -> io::Result<Self> {
+        return Self::from_bytes(map_file(path)?, domain);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::farkle::Dice;
+    use crate::hash::Sparse;
+    use crate::optimal::{MappedStrat, OptimalStrat};
+
+    #[test]
+    fn flat_round_trip() {
+        let mut map: PerfectHashMap<[bool; 6], (f32, DiceSetSample)> = PerfectHashMap::new();
+        for (i, (_, value)) in map.iter_mut().enumerate() {
+            *value = (i as f32 * 0.5, DiceSetSample::from_faces([1, 0, 5, 0, 0, (i % 7) as u8]).unwrap());
+        }
+        let mut bytes = Vec::new();
+        map.write_flat(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), HEADER_SIZE + <[bool; 6]>::SET_SIZE * 8);
+
+        let flat: FlatPerfectHashMap<[bool; 6], (f32, DiceSetSample), Vec<u8>> = FlatPerfectHashMap::from_bytes(bytes).unwrap();
+        assert_eq!(flat.iter().len(), map.iter().len());
+        for ((key, value), (flat_key, flat_value)) in map.iter().zip(flat.iter()) {
+            assert_eq!((key, value.clone()), (flat_key, flat_value.clone()));
+            assert_eq!(flat.get(&key), Some(value.clone()));
+        }
+
+        // Unwritten keys of a sparse map are written out as the default
+        let mut dense: PerfectHashMap<[bool; 6], f32> = PerfectHashMap::new();
+        let mut sparse: PerfectHashMap<[bool; 6], f32, Sparse<f32>> = PerfectHashMap::new();
+        dense[[true; 6]] = 0.25;
+        sparse[[true; 6]] = 0.25;
+        let (mut dense_bytes, mut sparse_bytes) = (Vec::new(), Vec::new());
+        dense.write_flat(&mut dense_bytes).unwrap();
+        sparse.write_flat(&mut sparse_bytes).unwrap();
+        assert_eq!(dense_bytes, sparse_bytes);
+    }
+
+    #[test]
+    fn mapped_matches_optimal() {
+        let dir = std::env::temp_dir().join(format!("kcd_table_{}", std::process::id()));
+        let optimal: OptimalStrat = OptimalStrat::with_domain([Dice::default(); 6], ScoreDomain::new(50, 100).unwrap()).iterate();
+        optimal.save_tables(&dir).unwrap();
+        let mapped = MappedStrat::open(&dir).unwrap();
+        assert_eq!((mapped.n, mapped.domain()), (optimal.n, optimal.domain()));
+
+        for ((score, dice), &value) in optimal.expected_scores.iter() {
+            assert_eq!(mapped.query_score(score, dice), value);
+        }
+        for (dice, &prob) in optimal.bust_prob.iter() {
+            assert_eq!(mapped.bust_prob.get(&dice), Some(prob));
+        }
+        let samples = (0..DiceSetSample::SET_SIZE)
+            .map(|h| DiceSetSample::from_perfhash(PerfectHash::new(h)))
+            .filter(|sample| sample.faces().iter().all(|&face| face != 0));
+        for sample in samples {
+            for score in optimal.domain().scores() {
+                assert_eq!(mapped.query_decision(score, sample.clone()), optimal.query_decision(score, sample.clone()));
+                if sample.faces() == [1, 5, 2, 2, 3, 6] {
+                    assert_eq!(mapped.explain_decision(score, &sample), optimal.explain_decision(score, &sample));
+                }
+            }
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn corrupt_tables_rejected() {
+        let map: PerfectHashMap<[bool; 6], f32> = PerfectHashMap::new();
+        let mut bytes = Vec::new();
+        map.write_flat(&mut bytes).unwrap();
+        let error = |data: Vec<u8>| FlatPerfectHashMap::<[bool; 6], f32, Vec<u8>>::from_bytes(data).err().unwrap().to_string();
+
+        assert_eq!(error(Vec::new()), "Not a flat table");
+        assert_eq!(error(bytes[..HEADER_SIZE - 1].to_vec()), "Not a flat table");
+        let mut wrong_magic = bytes.clone();
+        wrong_magic[7] = 2;
+        assert_eq!(error(wrong_magic), "Not a flat table");
+        let mut wrong_size = bytes.clone();
+        wrong_size[16] = 8;
+        assert_eq!(error(wrong_size), "Flat table was written with different key or value types");
+        assert_eq!(error(bytes[..bytes.len() - 1].to_vec()), "Flat table has the wrong length");
+        assert!(FlatPerfectHashMap::<[bool; 6], f64, Vec<u8>>::from_bytes(bytes.clone()).is_err());
+        assert!(FlatPerfectHashMap::<[bool; 6], f32, Vec<u8>>::from_bytes(bytes).is_ok());
+
+        let scores: ScoreMap<[bool; 6], f32> = ScoreMap::new(ScoreDomain::new(50, 100).unwrap());
+        let mut bytes = Vec::new();
+        scores.write_flat(&mut bytes).unwrap();
+        assert!(FlatScoreMap::<[bool; 6], f32, Vec<u8>>::from_bytes(bytes.clone(), ScoreDomain::new(50, 200).unwrap()).is_err());
+        assert!(FlatScoreMap::<[bool; 6], f32, Vec<u8>>::from_bytes(bytes, ScoreDomain::new(50, 100).unwrap()).is_ok());
+    }
+}
@@ -0,0 +1,145 @@
+//! Rules of thumb extracted from a solved strategy: the turn score from which to bank rather than roll
+//! the dice remaining.
+//!
+//! Rolling is preferred while the expected score gain of rolling the remaining dice is positive. This is
+//! usually a simple threshold, but isn't guaranteed to be (e.g near the cap), so any scores past the
+//! threshold where rolling is preferred again are reported.
+//!
+//! Thresholds are reported for every set of dice that can remain, so with a loadout of differing dice
+//! it shows how much it matters which dice are left. Compare loadouts by solving a strategy for each.
+
+use crate::farkle::FarkleScore;
+use crate::hash::{PerfectHash, PerfectHashing};
+use crate::numeric::Numeric;
+use crate::optimal::OptimalStrat;
+use std::fmt;
+
+/// When to bank with a given set of dice remaining
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BankThreshold {
+    /// The dice remaining
+    pub dice: [bool; 6],
+    /// Lowest score at which banking is preferred. None if rolling is preferred at every score.
+    pub threshold: Option<FarkleScore>,
+    /// Ranges of scores (inclusive) above the threshold where rolling is preferred again
+    pub exceptions: Vec<(FarkleScore, FarkleScore)>,
+}
+impl BankThreshold {
+    pub fn new<T: Numeric>(optimal: &OptimalStrat<T>, dice: [bool; 6]) -> Self {
+        let mut threshold = None;
+        let mut exceptions = Vec::new();
+        let mut exception: Option<(FarkleScore, FarkleScore)> = None;
+        for p in optimal.domain().scores() {
+            let roll = optimal.query_score(p, dice) > T::zero();
+            if threshold.is_none() {
+                if !roll {
+                    threshold = Some(p);
+                }
+            } else if roll {
+                exception = Some((exception.map_or(p, |(start, _)| start), p));
+            } else if let Some(range) = exception.take() {
+                exceptions.push(range);
+            }
+        }
+        exceptions.extend(exception);
+        return Self {dice, threshold, exceptions};
+    }
+
+    pub fn dice_count(&self) -> usize {
+        return self.dice.iter().filter(|&&b| b).count();
+    }
+
+    /// Whether banking from the threshold onwards matches the strategy at every score
+    pub fn is_simple(&self) -> bool {
+        return self.exceptions.is_empty();
+    }
+}
+
+/// Bank thresholds for every set of dice that can remain
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ThresholdReport {
+    pub thresholds: Vec<BankThreshold>,
+}
+impl ThresholdReport {
+    pub fn new<T: Numeric>(optimal: &OptimalStrat<T>) -> Self {
+        let thresholds = (1..<[bool; 6]>::SET_SIZE)
+            .map(|h| BankThreshold::new(optimal, <[bool; 6]>::from_perfhash(PerfectHash::new(h))))
+            .collect();
+        return Self {thresholds};
+    }
+
+    /// Lowest and highest threshold across every set of the given number of dice. The two are the same
+    /// when the dice are identical. None stands for never banking.
+    pub fn range_for_count(&self, count: usize) -> Option<(Option<FarkleScore>, Option<FarkleScore>)> {
+        let thresholds = self.thresholds.iter().filter(|t| t.dice_count() == count).map(|t| t.threshold);
+        // None (never bank) is treated as higher than any score
+        let key = |t: &Option<FarkleScore>| t.map_or(u32::MAX, |p| p.score());
+        let lowest = thresholds.clone().min_by_key(key)?;
+        let highest = thresholds.max_by_key(key)?;
+        return Some((lowest, highest));
+    }
+
+    /// Every set of dice where the strategy isn't a simple threshold
+    pub fn non_monotonic(&self) -> impl Iterator<Item = &BankThreshold> {
+        return self.thresholds.iter().filter(|t| !t.is_simple());
+    }
+}
+impl fmt::Display for ThresholdReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for count in (1..=6).rev() {
+            let Some((lowest, highest)) = self.range_for_count(count) else {
+                continue;
+            };
+            // Never banking is the highest threshold so only the highest can be None
+            match (lowest, highest) {
+                (None, _) => writeln!(f, "{} dice left: never bank", count)?,
+                (Some(low), Some(high)) if low == high => writeln!(f, "{} dice left: bank from {}", count, low.score())?,
+                (Some(low), Some(high)) => {
+                    writeln!(f, "{} dice left: bank from {} to {} depending on the dice", count, low.score(), high.score())?;
+                }
+                (Some(low), None) => writeln!(f, "{} dice left: bank from {} or never depending on the dice", count, low.score())?,
+            }
+        }
+        for t in self.non_monotonic() {
+            let dice: String = t.dice.iter().map(|&b| if b { '1' } else { '0' }).collect();
+            let ranges: Vec<String> = t.exceptions.iter().map(|(start, end)| format!("{}-{}", start.score(), end.score())).collect();
+            writeln!(f, "Dice {} aren't a simple threshold, rolling is preferred again at {}", dice, ranges.join(", "))?;
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::farkle::{Dice, ScoreDomain};
+
+    #[test]
+    fn thresholds() {
+        let mut optimal: OptimalStrat = OptimalStrat::with_domain([Dice::default(); 6], ScoreDomain::new(50, 1000).unwrap());
+        let report = ThresholdReport::new(&optimal);
+        assert_eq!(report.thresholds.len(), 63);
+        // A single die gains 25 on average from 0 but busts 2/3 of the time
+        let one = FarkleScore::new(50);
+        assert_eq!(report.range_for_count(1), Some((Some(one), Some(one))));
+        assert_eq!(report.range_for_count(6), Some((None, None)));
+        assert_eq!(report.non_monotonic().count(), 0);
+        assert!(report.to_string().contains("1 dice left: bank from 50\n"));
+        assert!(report.to_string().starts_with("6 dice left: never bank\n"));
+
+        // Force rolling to look worth it again for a few scores
+        let single = [true, false, false, false, false, false];
+        for p in [200, 250, 400] {
+            optimal.expected_scores[(FarkleScore::new(p), single)] = 1.0;
+        }
+        let threshold = BankThreshold::new(&optimal, single);
+        assert_eq!(threshold.threshold, Some(one));
+        assert_eq!(threshold.exceptions, vec![
+            (FarkleScore::new(200), FarkleScore::new(250)),
+            (FarkleScore::new(400), FarkleScore::new(400)),
+        ]);
+        let report = ThresholdReport::new(&optimal);
+        assert!(report.to_string().contains("Dice 100000 aren't a simple threshold, rolling is preferred again at 200-250, 400-400\n"));
+        assert!(report.to_string().contains("1 dice left: bank from 50\n"));
+    }
+}
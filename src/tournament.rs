@@ -0,0 +1,309 @@
+//! Round-robin tournaments between registered strategies, rated with Elo
+
+use crate::farkle::Dice;
+use crate::game::{derive_seed, play_match, MatchResult};
+use crate::parallel::map_indexed;
+use crate::strategy::Strategy;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+
+/// Rating every entrant starts with
+const ELO_START: f64 = 1500.0;
+/// How much a single match can move a rating
+const ELO_K: f64 = 16.0;
+
+/// A strategy taking part in a tournament along with the dice it plays with
+pub struct Entrant {
+    pub name: String,
+    pub strategy: Box<dyn Strategy + Sync>,
+    pub dices: [Dice; 6],
+}
+
+/// Manages the entrants and settings of a round-robin tournament
+pub struct Tournament {
+    entrants: Vec<Entrant>,
+    /// Number of matches played between each pair of entrants. Who goes first alternates between matches.
+    pub matches_per_pairing: usize,
+    /// Score needed to win a match
+    pub target: u32,
+    /// Seed every match's rng is derived from
+    pub seed: u64,
+}
+impl Tournament {
+    pub fn new(matches_per_pairing: usize, target: u32, seed: u64) -> Self {
+        return Self {entrants: Vec::new(), matches_per_pairing, target, seed};
+    }
+
+    /// Adds a strategy to the tournament
+    pub fn register(&mut self, name: impl Into<String>, strategy: impl Strategy + Sync + 'static, dices: [Dice; 6]) {
+        self.entrants.push(Entrant {name: name.into(), strategy: Box::new(strategy), dices});
+    }
+
+    /// Plays out every match of the tournament.
+    ///
+    /// Each match has its own rng seeded from the tournament seed and the match's position in the
+    /// schedule, so results are reproducible no matter how the matches get spread over threads.
+    pub fn run(&self) -> TournamentReport {
+        // Schedule is ordered by round first so pairings are interleaved when updating Elo
+        let mut schedule = Vec::new();
+        for k in 0..self.matches_per_pairing {
+            for i in 0..self.entrants.len() {
+                for j in (i + 1)..self.entrants.len() {
+                    // Alternate who goes first
+                    if k % 2 == 0 {
+                        schedule.push([i, j]);
+                    } else {
+                        schedule.push([j, i]);
+                    }
+                }
+            }
+        }
+        let results: Vec<MatchResult> = map_indexed(&schedule, true, |m, &[a, b]| {
+            let mut rng = StdRng::seed_from_u64(derive_seed(self.seed, m as u64));
+            let players = [&*self.entrants[a].strategy, &*self.entrants[b].strategy];
+            let dices = [&self.entrants[a].dices, &self.entrants[b].dices];
+            return play_match(players, dices, self.target, &mut rng);
+        });
+
+        let n = self.entrants.len();
+        let mut elo = vec![ELO_START; n];
+        let mut played = vec![0usize; n];
+        let mut wins = vec![0usize; n];
+        let mut draws = vec![0usize; n];
+        let mut total_rounds = vec![0usize; n];
+        let mut pairings = Vec::new();
+        for i in 0..n {
+            for j in (i + 1)..n {
+                pairings.push(PairingResult {
+                    players: [self.entrants[i].name.clone(), self.entrants[j].name.clone()],
+                    wins: [0, 0],
+                    draws: 0,
+                    average_rounds: 0.0,
+                });
+            }
+        }
+        for (&players, result) in schedule.iter().zip(results.iter()) {
+            for &p in players.iter() {
+                played[p] += 1;
+                total_rounds[p] += result.rounds;
+            }
+            // Standard Elo update, where a draw counts as half a win for each player
+            let [first, second] = players;
+            let first_score = match result.winner {
+                Some(w) => if w == 0 { 1.0 } else { 0.0 },
+                None => 0.5,
+            };
+            let expected_first = 1.0 / (1.0 + 10f64.powf((elo[second] - elo[first]) / 400.0));
+            elo[first] += ELO_K * (first_score - expected_first);
+            elo[second] -= ELO_K * (first_score - expected_first);
+            // Record result against the pairing
+            let (i, j) = (first.min(second), first.max(second));
+            let pairing = &mut pairings[pairing_index(n, i, j)];
+            match result.winner {
+                Some(w) => {
+                    let winner = players[w];
+                    wins[winner] += 1;
+                    pairing.wins[if winner == i {0} else {1}] += 1;
+                }
+                None => {
+                    draws[first] += 1;
+                    draws[second] += 1;
+                    pairing.draws += 1;
+                }
+            }
+            pairing.average_rounds += result.rounds as f64 / self.matches_per_pairing as f64;
+        }
+
+        let standings = (0..n).map(|i| Standing {
+            name: self.entrants[i].name.clone(),
+            played: played[i],
+            wins: wins[i],
+            draws: draws[i],
+            win_rate: wins[i] as f64 / played[i].max(1) as f64,
+            average_rounds: total_rounds[i] as f64 / played[i].max(1) as f64,
+            elo: elo[i],
+        }).collect();
+        return TournamentReport {seed: self.seed, target: self.target, standings, pairings};
+    }
+}
+
+/// Index of the pairing (i, j) with i < j in the order pairings are generated
+fn pairing_index(n: usize, i: usize, j: usize) -> usize {
+    return i * n - i * (i + 1) / 2 + (j - i - 1);
+}
+
+/// Overall results of a single entrant
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Standing {
+    pub name: String,
+    /// Matches played
+    pub played: usize,
+    /// Matches won
+    pub wins: usize,
+    /// Matches drawn (see game::MAX_ROUNDS)
+    pub draws: usize,
+    pub win_rate: f64,
+    /// Average number of rounds (from both players) the entrant's matches lasted
+    pub average_rounds: f64,
+    pub elo: f64,
+}
+
+/// Head to head results between 2 entrants
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PairingResult {
+    pub players: [String; 2],
+    /// Matches won by each player
+    pub wins: [usize; 2],
+    /// Matches drawn (see game::MAX_ROUNDS)
+    pub draws: usize,
+    /// Average number of rounds the matches lasted
+    pub average_rounds: f64,
+}
+
+/// Results of a tournament
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TournamentReport {
+    pub seed: u64,
+    pub target: u32,
+    pub standings: Vec<Standing>,
+    pub pairings: Vec<PairingResult>,
+}
+impl TournamentReport {
+    pub fn to_json(&self) -> String {
+        return serde_json::to_string_pretty(self).expect("Failed to serialize report");
+    }
+
+    /// CSV of the standings, one row per entrant
+    pub fn standings_csv(&self) -> String {
+        let mut out = String::from("name,played,wins,draws,win_rate,average_rounds,elo\n");
+        for s in self.standings.iter() {
+            out += &format!("{},{},{},{},{},{},{}\n", csv_field(&s.name), s.played, s.wins, s.draws, s.win_rate, s.average_rounds, s.elo);
+        }
+        return out;
+    }
+
+    /// CSV of the head to head results, one row per pairing
+    pub fn pairings_csv(&self) -> String {
+        let mut out = String::from("player_1,player_2,wins_1,wins_2,draws,average_rounds\n");
+        for p in self.pairings.iter() {
+            out += &format!(
+                "{},{},{},{},{},{}\n",
+                csv_field(&p.players[0]), csv_field(&p.players[1]), p.wins[0], p.wins[1], p.draws, p.average_rounds,
+            );
+        }
+        return out;
+    }
+}
+
+/// Quotes a CSV field if needed
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        return format!("\"{}\"", field.replace('"', "\"\""));
+    }
+    return field.to_string();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::farkle::DiceSetSample;
+    use crate::heuristic::{KeepRule, ThresholdStrat};
+    use crate::strategy::TurnState;
+
+    fn tournament(seed: u64) -> Tournament {
+        let mut tournament = Tournament::new(4, 1000, seed);
+        tournament.register("Max keep, always bank", ThresholdStrat::uniform(0, KeepRule::Max), [Dice::default(); 6]);
+        tournament.register("bank at 300", ThresholdStrat::uniform(300, KeepRule::Max), [Dice::default(); 6]);
+        tournament.register("bank at 1000", ThresholdStrat::uniform(1000, KeepRule::Minimum), [Dice::default(); 6]);
+        return tournament;
+    }
+
+    #[test]
+    fn round_robin() {
+        let report = tournament(3).run();
+        assert_eq!(report.standings.len(), 3);
+        assert_eq!(report.pairings.iter().map(|p| p.players.clone()).collect::<Vec<_>>(), vec![
+            ["Max keep, always bank".to_string(), "bank at 300".to_string()],
+            ["Max keep, always bank".to_string(), "bank at 1000".to_string()],
+            ["bank at 300".to_string(), "bank at 1000".to_string()],
+        ]);
+        for pairing in report.pairings.iter() {
+            assert_eq!(pairing.wins[0] + pairing.wins[1], 4);
+            assert!(pairing.average_rounds >= 2.0);
+        }
+        for standing in report.standings.iter() {
+            assert_eq!(standing.played, 8);
+            assert_eq!(standing.win_rate, standing.wins as f64 / 8.0);
+        }
+        assert_eq!(report.standings.iter().map(|s| s.wins).sum::<usize>(), 12);
+        // Elo moves between players, so the total stays the same
+        let total_elo: f64 = report.standings.iter().map(|s| s.elo).sum();
+        assert!((total_elo - 3.0 * ELO_START).abs() < 1e-9);
+        let best = report.standings.iter().max_by_key(|s| s.wins).unwrap();
+        assert!(best.elo > ELO_START);
+
+        // Same seed, same tournament
+        let again = tournament(3).run();
+        assert_eq!(again.to_json(), report.to_json());
+    }
+
+    #[test]
+    fn elo_update() {
+        let mut tournament = Tournament::new(1, 1000, 0);
+        tournament.register("a", ThresholdStrat::uniform(300, KeepRule::Max), [Dice::default(); 6]);
+        tournament.register("b", ThresholdStrat::uniform(300, KeepRule::Max), [Dice::default(); 6]);
+        let report = tournament.run();
+        // Evenly rated players exchange half of K
+        let winner = report.standings.iter().find(|s| s.wins == 1).unwrap();
+        assert_eq!(winner.elo, ELO_START + ELO_K / 2.0);
+    }
+
+    #[test]
+    fn draws() {
+        /// Never keeps anything, so never scores
+        struct Forfeit;
+        impl Strategy for Forfeit {
+            fn decide(&self, _state: &TurnState, _sample: &DiceSetSample) -> (DiceSetSample, bool) {
+                return (DiceSetSample::default(), true);
+            }
+        }
+        let mut tournament = Tournament::new(2, 1000, 0);
+        tournament.register("a", Forfeit, [Dice::default(); 6]);
+        tournament.register("b", Forfeit, [Dice::default(); 6]);
+        let report = tournament.run();
+        assert_eq!((report.pairings[0].wins, report.pairings[0].draws), ([0, 0], 2));
+        for standing in report.standings.iter() {
+            assert_eq!((standing.wins, standing.draws, standing.elo), (0, 2, ELO_START));
+        }
+        assert!(report.pairings_csv().ends_with("\na,b,0,0,2,10000\n"));
+    }
+
+    #[test]
+    fn pairing_indices() {
+        let n = 5;
+        let mut k = 0;
+        for i in 0..n {
+            for j in (i + 1)..n {
+                assert_eq!(pairing_index(n, i, j), k);
+                k += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn exports() {
+        let report = tournament(3).run();
+        let standings = report.standings_csv();
+        assert_eq!(standings.lines().count(), 4);
+        assert!(standings.starts_with("name,played,wins,draws,win_rate,average_rounds,elo\n\"Max keep, always bank\",8,"));
+        let pairings = report.pairings_csv();
+        assert_eq!(pairings.lines().count(), 4);
+        assert!(pairings.lines().nth(3).unwrap().starts_with("bank at 300,bank at 1000,"));
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+
+        let parsed: TournamentReport = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!((parsed.seed, parsed.target), (3, 1000));
+        assert_eq!(parsed.to_json(), report.to_json());
+    }
+}
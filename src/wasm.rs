@@ -0,0 +1,135 @@
+//! wasm-bindgen exports (built with the wasm feature) for a browser based advisor.
+//!
+//! Build for the browser without the default features, e.g
+//! `cargo build --lib --release --target wasm32-unknown-unknown --no-default-features --features wasm`
+//! then run wasm-bindgen over the output. The advisor is given the files written by
+//! OptimalStrat::save_tables() as bytes, so nothing is solved in the browser. Policy is a much smaller
+//! alternative when only decisions are needed.
+//!
+//! Samples use the same encoding as the ffi module: 6 faces, one per die, with 0 for a missing die.
+//! Dice masks have bit i set when die i is present.
+
+use crate::farkle::{self, DiceSetSample, DiceSide, FarkleScore};
+use crate::optimal::MappedStrat;
+use crate::policy::PolicyReader;
+use wasm_bindgen::prelude::*;
+
+/// Converts a slice of faces from JavaScript into a sample
+fn sample_from_js(faces: &[u8]) -> Result<DiceSetSample, JsError> {
+    let faces: [u8; 6] = faces.try_into().map_err(|_| JsError::new("Samples need exactly 6 faces"))?;
+    return DiceSetSample::from_faces(faces).ok_or_else(|| JsError::new("Faces must be 0 to 6"));
+}
+
+/// Places the faces kept back onto the dice that rolled them. None if a face isn't 1 to 6 or wasn't rolled.
+fn place_kept(sample: &DiceSetSample, kept: &[u8]) -> Option<DiceSetSample> {
+    let mut kept_sides = Vec::new();
+    for &face in kept {
+        if !(1..=6).contains(&face) {
+            return None;
+        }
+        kept_sides.push(DiceSide::from(face - 1));
+    }
+    return sample.select_sides(&kept_sides);
+}
+
+/// Highest score that can be made from the faces rolled (1 to 6, with 0 for a missing die)
+#[wasm_bindgen(js_name = scoreRoll)]
+pub fn score_roll(faces: &[u8]) -> Result<u32, JsError> {
+    let sample = sample_from_js(faces)?;
+    return Ok(farkle::best_score(farkle::count_sides(&sample.present())).score());
+}
+
+/// The dice to keep from a roll and whether to roll again afterwards
+#[wasm_bindgen]
+pub struct Decision {
+    selection: [u8; 6],
+    #[wasm_bindgen(js_name = rollAgain)]
+    pub roll_again: bool,
+}
+#[wasm_bindgen]
+impl Decision {
+    /// Faces of the dice to keep, with 0 for the dice not kept
+    #[wasm_bindgen(getter)]
+    pub fn selection(&self) -> Vec<u8> {
+        return self.selection.to_vec();
+    }
+}
+
+/// Read-only optimal strategy queried from precomputed tables
+#[wasm_bindgen]
+pub struct Advisor {
+    strat: MappedStrat<Vec<u8>>,
+}
+#[wasm_bindgen]
+impl Advisor {
+    /// Creates the advisor from the contents of meta.json, expected_scores.bin, expected_hold.bin and
+    /// bust_prob.bin written by OptimalStrat::save_tables()
+    #[wasm_bindgen(constructor)]
+    pub fn new(meta: &str, expected_scores: Vec<u8>, expected_hold: Vec<u8>, bust_prob: Vec<u8>) -> Result<Advisor, JsError> {
+        let strat = MappedStrat::from_table_bytes(meta, expected_scores, expected_hold, bust_prob)?;
+        return Ok(Self {strat});
+    }
+
+    fn check_score(&self, score: u32) -> Result<FarkleScore, JsError> {
+        let score = FarkleScore::new(score);
+        if self.strat.domain().index(score).is_none() {
+            return Err(JsError::new("Score lies outside of the score domain"));
+        }
+        return Ok(score);
+    }
+
+    /// Expected score gain with the given current score and mask of dice left
+    #[wasm_bindgen(js_name = queryScore)]
+    pub fn query_score(&self, score: u32, mask: u8) -> Result<f32, JsError> {
+        let score = self.check_score(score)?;
+        return Ok(self.strat.query_score(score, std::array::from_fn(|i| mask & (1 << i) != 0)));
+    }
+
+    /// The decision to make with the given current score and faces rolled
+    #[wasm_bindgen(js_name = queryDecision)]
+    pub fn query_decision(&self, score: u32, faces: &[u8]) -> Result<Decision, JsError> {
+        let score = self.check_score(score)?;
+        let (selection, roll_again) = self.strat.query_decision(score, sample_from_js(faces)?);
+        return Ok(Decision {selection: selection.faces(), roll_again});
+    }
+}
+
+/// Decisions read from a policy table (see the policy module)
+#[wasm_bindgen]
+pub struct Policy {
+    reader: PolicyReader,
+}
+#[wasm_bindgen]
+impl Policy {
+    /// Creates the policy from the bytes written by export_policy()
+    #[wasm_bindgen(constructor)]
+    pub fn new(bytes: Vec<u8>) -> Result<Policy, JsError> {
+        return Ok(Self {reader: PolicyReader::from_bytes(bytes)?});
+    }
+
+    /// The decision to make with the given current score and faces rolled
+    #[wasm_bindgen(js_name = queryDecision)]
+    pub fn query_decision(&self, score: u32, faces: &[u8]) -> Result<Decision, JsError> {
+        let sample = sample_from_js(faces)?;
+        let rolled: Vec<u8> = sample.present().iter().map(|side| side.face()).collect();
+        let (kept, roll_again) = self.reader.decide(score, &rolled).ok_or_else(|| JsError::new("Score lies outside of the score domain or no dice were rolled"))?;
+        let selection = place_kept(&sample, &kept).ok_or_else(|| JsError::new("Policy table is corrupt"))?;
+        return Ok(Decision {selection: selection.faces(), roll_again});
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kept_faces_placed() {
+        let sample = DiceSetSample::from_faces([5, 2, 1, 0, 5, 3]).unwrap();
+        assert_eq!(place_kept(&sample, &[1, 5]).unwrap().faces(), [5, 0, 1, 0, 0, 0]);
+        // Corrupt tables can give faces that weren't rolled or aren't faces at all
+        assert!(place_kept(&sample, &[6]).is_none());
+        assert!(place_kept(&sample, &[1, 1]).is_none());
+        assert!(place_kept(&sample, &[0]).is_none());
+        assert!(place_kept(&sample, &[7]).is_none());
+    }
+}